@@ -0,0 +1,6 @@
+CREATE TABLE users (
+    user_id uuid NOT NULL,
+    username TEXT NOT NULL UNIQUE,
+    password_hash TEXT NOT NULL,
+    PRIMARY KEY (user_id)
+);
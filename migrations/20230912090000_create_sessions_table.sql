@@ -0,0 +1,8 @@
+CREATE TABLE sessions (
+    session_key TEXT NOT NULL,
+    session_state JSONB NOT NULL,
+    expires_at timestamptz NOT NULL,
+    PRIMARY KEY (session_key)
+);
+
+CREATE INDEX sessions_expires_at_idx ON sessions (expires_at);
@@ -0,0 +1,8 @@
+-- Every newsletter issue carries a fresh token in its unsubscribe link. Like
+-- a password, only its hex-encoded SHA-256 digest is stored.
+CREATE TABLE unsubscribe_tokens (
+    unsubscribe_token_hash TEXT NOT NULL,
+    subscriber_id UUID NOT NULL
+        REFERENCES subscriptions (id),
+    PRIMARY KEY (unsubscribe_token_hash)
+);
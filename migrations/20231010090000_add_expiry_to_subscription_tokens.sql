@@ -0,0 +1,9 @@
+ALTER TABLE subscription_tokens
+    ADD COLUMN created_at timestamptz NOT NULL DEFAULT now(),
+    ADD COLUMN expires_at timestamptz,
+    ADD COLUMN consumed_at timestamptz;
+
+-- Tokens handed out before they could expire get a day from now.
+UPDATE subscription_tokens SET expires_at = now() + interval '1 day';
+
+ALTER TABLE subscription_tokens ALTER COLUMN expires_at SET NOT NULL;
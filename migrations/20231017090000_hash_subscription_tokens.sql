@@ -0,0 +1,7 @@
+-- Only a SHA-256 digest of each confirmation token is kept from now on,
+-- hex-encoded. Tokens already sent out keep working: their links carry the
+-- plaintext, which hashes to the digest stored here.
+ALTER TABLE subscription_tokens RENAME COLUMN subscription_token TO subscription_token_hash;
+
+UPDATE subscription_tokens
+SET subscription_token_hash = encode(sha256(convert_to(subscription_token_hash, 'UTF8')), 'hex');
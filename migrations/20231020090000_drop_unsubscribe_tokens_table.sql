@@ -0,0 +1,3 @@
+-- Unsubscribe links are signed now and carry everything they need. Links
+-- sent with one of these tokens stop working.
+DROP TABLE unsubscribe_tokens;
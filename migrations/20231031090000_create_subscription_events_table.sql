@@ -0,0 +1,16 @@
+CREATE TYPE subscription_actor AS ENUM ('subscriber', 'admin', 'system');
+
+CREATE TABLE subscription_events (
+    id BIGSERIAL PRIMARY KEY,
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    occurred_at timestamptz NOT NULL DEFAULT now(),
+    -- NULL for the event that created the subscription.
+    old_status subscription_status,
+    new_status subscription_status NOT NULL,
+    actor subscription_actor NOT NULL,
+    ip_address TEXT,
+    user_agent TEXT
+);
+
+CREATE INDEX subscription_events_subscriber_id_idx
+    ON subscription_events (subscriber_id, occurred_at);
@@ -0,0 +1,16 @@
+-- One row per subscription form submission, completed when the subscriber
+-- confirms. Subscribers from before this table existed have no records.
+CREATE TABLE consent_records (
+    id BIGSERIAL PRIMARY KEY,
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id),
+    privacy_policy_version TEXT NOT NULL,
+    form_source TEXT,
+    consented_at timestamptz NOT NULL,
+    consent_ip_address TEXT,
+    consent_user_agent TEXT,
+    confirmed_at timestamptz,
+    confirmation_ip_address TEXT,
+    confirmation_user_agent TEXT
+);
+
+CREATE INDEX consent_records_subscriber_id_idx ON consent_records (subscriber_id);
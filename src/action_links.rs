@@ -0,0 +1,242 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
+use sha2::Sha256;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// What a signed link lets its holder do. A token signed for one action is
+/// rejected by every other.
+///
+/// Confirmation links are not signed: they have to be single-use, and
+/// resending a confirmation revokes the previous link, so they stay stored
+/// tokens. There is no preferences page yet for a link to point to.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LinkAction {
+    Unsubscribe,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum ActionLinkError {
+    #[error("The link token is malformed.")]
+    Malformed,
+    #[error("The link token signature is invalid.")]
+    InvalidSignature,
+    #[error("The link token was issued for another action.")]
+    WrongAction,
+    #[error("The link token has expired.")]
+    Expired,
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Claims {
+    #[serde(rename = "kid")]
+    key_id: String,
+    #[serde(rename = "sub")]
+    subscriber_id: Uuid,
+    #[serde(rename = "act")]
+    action: LinkAction,
+    #[serde(rename = "exp")]
+    expires_at: i64,
+}
+
+/// Signs and verifies stateless action links: URL-safe tokens carrying the
+/// subscriber id, the action and an expiry, authenticated with HMAC-SHA256.
+///
+/// Several keys can be active at once. New tokens are always signed with the
+/// current key, while tokens signed with any other configured key keep
+/// verifying until that key is removed, which is how keys are rotated.
+#[derive(Clone, Debug)]
+pub struct ActionLinkSigner {
+    current_key_id: String,
+    keys: HashMap<String, Secret<String>>,
+}
+
+impl ActionLinkSigner {
+    pub fn new(
+        current_key_id: impl Into<String>,
+        keys: HashMap<String, Secret<String>>,
+    ) -> Result<Self, anyhow::Error> {
+        let current_key_id = current_key_id.into();
+        if !keys.contains_key(&current_key_id) {
+            anyhow::bail!(
+                "The current signing key `{}` is not among the configured keys.",
+                current_key_id
+            );
+        }
+
+        Ok(Self {
+            current_key_id,
+            keys,
+        })
+    }
+
+    pub fn sign(
+        &self,
+        subscriber_id: Uuid,
+        action: LinkAction,
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        let claims = Claims {
+            key_id: self.current_key_id.clone(),
+            subscriber_id,
+            action,
+            expires_at: expires_at.timestamp(),
+        };
+        let payload = URL_SAFE_NO_PAD
+            .encode(serde_json::to_vec(&claims).expect("Failed to serialize link claims."));
+        let signature = self
+            .mac(&self.keys[&self.current_key_id], &payload)
+            .finalize();
+
+        format!(
+            "{}.{}",
+            payload,
+            URL_SAFE_NO_PAD.encode(signature.into_bytes())
+        )
+    }
+
+    /// Returns the subscriber the token was issued for, provided it carries a
+    /// valid signature, was issued for `action` and has not expired.
+    pub fn verify(&self, token: &str, action: LinkAction) -> Result<Uuid, ActionLinkError> {
+        let (payload, signature) = token.split_once('.').ok_or(ActionLinkError::Malformed)?;
+        let claims: Claims = URL_SAFE_NO_PAD
+            .decode(payload)
+            .ok()
+            .and_then(|claims| serde_json::from_slice(&claims).ok())
+            .ok_or(ActionLinkError::Malformed)?;
+        let signature = URL_SAFE_NO_PAD
+            .decode(signature)
+            .map_err(|_| ActionLinkError::Malformed)?;
+
+        let key = self
+            .keys
+            .get(&claims.key_id)
+            .ok_or(ActionLinkError::InvalidSignature)?;
+        self.mac(key, payload)
+            .verify_slice(&signature)
+            .map_err(|_| ActionLinkError::InvalidSignature)?;
+
+        if claims.action != action {
+            return Err(ActionLinkError::WrongAction);
+        }
+        if claims.expires_at <= Utc::now().timestamp() {
+            return Err(ActionLinkError::Expired);
+        }
+
+        Ok(claims.subscriber_id)
+    }
+
+    fn mac(&self, key: &Secret<String>, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(key.expose_secret().as_bytes())
+            .expect("HMAC accepts keys of any length.");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::action_links::{ActionLinkError, ActionLinkSigner, LinkAction};
+    use chrono::{Duration, Utc};
+    use claim::{assert_err, assert_ok};
+    use secrecy::Secret;
+    use std::collections::HashMap;
+    use uuid::Uuid;
+
+    fn signer(current_key_id: &str, key_ids: &[&str]) -> ActionLinkSigner {
+        let keys = key_ids
+            .iter()
+            .map(|id| (id.to_string(), Secret::new(format!("{}-secret", id))))
+            .collect::<HashMap<_, _>>();
+        ActionLinkSigner::new(current_key_id, keys).unwrap()
+    }
+
+    fn tomorrow() -> chrono::DateTime<Utc> {
+        Utc::now() + Duration::days(1)
+    }
+
+    #[test]
+    fn a_signed_token_verifies_to_its_subscriber() {
+        let signer = signer("a", &["a"]);
+        let subscriber_id = Uuid::new_v4();
+
+        let token = signer.sign(subscriber_id, LinkAction::Unsubscribe, tomorrow());
+
+        assert_eq!(
+            Ok(subscriber_id),
+            signer.verify(&token, LinkAction::Unsubscribe)
+        );
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c)));
+    }
+
+    #[test]
+    fn an_expired_token_is_rejected() {
+        let signer = signer("a", &["a"]);
+
+        let token = signer.sign(
+            Uuid::new_v4(),
+            LinkAction::Unsubscribe,
+            Utc::now() - Duration::seconds(1),
+        );
+
+        assert_eq!(
+            Err(ActionLinkError::Expired),
+            signer.verify(&token, LinkAction::Unsubscribe)
+        );
+    }
+
+    #[test]
+    fn a_tampered_token_is_rejected() {
+        let signer = signer("a", &["a"]);
+        let token = signer.sign(Uuid::new_v4(), LinkAction::Unsubscribe, tomorrow());
+        let forged_payload = signer
+            .sign(Uuid::new_v4(), LinkAction::Unsubscribe, tomorrow())
+            .split_once('.')
+            .unwrap()
+            .0
+            .to_owned();
+        let forged = format!("{}.{}", forged_payload, token.split_once('.').unwrap().1);
+
+        assert_eq!(
+            Err(ActionLinkError::InvalidSignature),
+            signer.verify(&forged, LinkAction::Unsubscribe)
+        );
+        assert_eq!(
+            Err(ActionLinkError::Malformed),
+            signer.verify("not-a-token", LinkAction::Unsubscribe)
+        );
+    }
+
+    #[test]
+    fn tokens_signed_with_a_retired_key_verify_until_the_key_is_removed() {
+        let old_signer = signer("old", &["old"]);
+        let subscriber_id = Uuid::new_v4();
+        let token = old_signer.sign(subscriber_id, LinkAction::Unsubscribe, tomorrow());
+
+        let rotated = signer("new", &["new", "old"]);
+        assert_eq!(
+            Ok(subscriber_id),
+            rotated.verify(&token, LinkAction::Unsubscribe)
+        );
+        let new_token = rotated.sign(subscriber_id, LinkAction::Unsubscribe, tomorrow());
+        assert_err!(old_signer.verify(&new_token, LinkAction::Unsubscribe));
+
+        let retired = signer("new", &["new"]);
+        assert_eq!(
+            Err(ActionLinkError::InvalidSignature),
+            retired.verify(&token, LinkAction::Unsubscribe)
+        );
+        assert_ok!(retired.verify(&new_token, LinkAction::Unsubscribe));
+    }
+
+    #[test]
+    fn the_current_key_must_be_configured() {
+        assert_err!(ActionLinkSigner::new("missing", HashMap::new()));
+    }
+}
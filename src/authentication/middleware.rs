@@ -0,0 +1,177 @@
+use crate::authentication::{validate_credentials, AuthError, Credentials};
+use crate::session_state::TypedSession;
+use crate::utils::{e500, see_other};
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderMap, HeaderValue};
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, FromRequest, HttpMessage, HttpResponse};
+use actix_web_lab::middleware::Next;
+use anyhow::Context;
+use base64::Engine;
+use secrecy::Secret;
+use sqlx::PgPool;
+use std::fmt::{Display, Formatter};
+use std::ops::Deref;
+use uuid::Uuid;
+
+#[derive(Copy, Clone, Debug)]
+pub struct UserId(Uuid);
+
+impl Display for UserId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl Deref for UserId {
+    type Target = Uuid;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Lets a request through if it carries either a logged-in session or valid
+/// HTTP Basic credentials. Anonymous browsers are sent to the login form,
+/// every other client gets a `401` with a Basic challenge.
+///
+/// Rejections are answered right here instead of being returned as errors:
+/// errors raised by middleware skip the application's error handlers.
+pub async fn reject_anonymous_users(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    match authenticate(&mut req).await {
+        Ok(user_id) => {
+            req.extensions_mut().insert(user_id);
+            next.call(req)
+                .await
+                .map(ServiceResponse::map_into_left_body)
+        }
+        Err(error) => Ok(req.error_response(error).map_into_right_body()),
+    }
+}
+
+async fn authenticate(req: &mut ServiceRequest) -> Result<UserId, actix_web::Error> {
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+
+    if let Some(user_id) = session.get_user_id().map_err(e500)? {
+        return Ok(UserId(user_id));
+    }
+
+    if !req.headers().contains_key(header::AUTHORIZATION) && prefers_html(req) {
+        let error = anyhow::anyhow!("The user has not logged in.");
+        return Err(InternalError::from_response(error, see_other("/login")).into());
+    }
+
+    let credentials = basic_authentication(req.headers()).map_err(unauthorized)?;
+
+    let connection = req
+        .app_data::<web::Data<PgPool>>()
+        .expect("The database pool is not registered as application data.")
+        .clone();
+
+    let user_id = validate_credentials(credentials, &connection)
+        .await
+        .map_err(|error| match error {
+            AuthError::InvalidCredentials(_) => unauthorized(error.into()),
+            AuthError::UnexpectedError(_) => actix_web::error::ErrorInternalServerError(error),
+        })?;
+
+    Ok(UserId(user_id))
+}
+
+fn prefers_html(req: &ServiceRequest) -> bool {
+    req.method() == actix_web::http::Method::GET
+        && req
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("text/html"))
+}
+
+fn unauthorized(error: anyhow::Error) -> actix_web::Error {
+    let mut response = HttpResponse::new(StatusCode::UNAUTHORIZED);
+    let header_value = HeaderValue::from_str(r#"Basic realm="admin""#).unwrap();
+    response
+        .headers_mut()
+        .insert(header::WWW_AUTHENTICATE, header_value);
+
+    InternalError::from_response(error, response).into()
+}
+
+fn basic_authentication(headers: &HeaderMap) -> Result<Credentials, anyhow::Error> {
+    let header_value = headers
+        .get("Authorization")
+        .context("The 'Authorization' header was missing.")?
+        .to_str()
+        .context("The 'Authorization' header was not a valid UTF8 string.")?;
+    let base64encoded_segment = header_value
+        .strip_prefix("Basic ")
+        .context("The authorization scheme was not 'Basic'.")?;
+    let decoded_bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64encoded_segment)
+        .context("Failed to base64-decode 'Basic' credentials.")?;
+    let decoded_credentials = String::from_utf8(decoded_bytes)
+        .context("The decoded credential string is not valid UTF8.")?;
+
+    let (username, password) = decoded_credentials
+        .split_once(':')
+        .context("A ':' must separate the username and the password in 'Basic' auth.")?;
+
+    Ok(Credentials {
+        username: username.to_string(),
+        password: Secret::new(password.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::authentication::middleware::basic_authentication;
+    use actix_web::http::header::{HeaderMap, HeaderValue, AUTHORIZATION};
+    use base64::Engine;
+    use claim::{assert_err, assert_ok};
+    use secrecy::ExposeSecret;
+
+    fn headers_with_authorization(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn valid_basic_credentials_are_parsed_successfully() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode("ursula:pass:word");
+        let headers = headers_with_authorization(&format!("Basic {}", encoded));
+
+        let credentials = assert_ok!(basic_authentication(&headers));
+
+        assert_eq!("ursula", credentials.username);
+        assert_eq!("pass:word", credentials.password.expose_secret());
+    }
+
+    #[test]
+    fn missing_authorization_header_is_rejected() {
+        assert_err!(basic_authentication(&HeaderMap::new()));
+    }
+
+    #[test]
+    fn non_basic_scheme_is_rejected() {
+        let headers = headers_with_authorization("Bearer some-token");
+
+        assert_err!(basic_authentication(&headers));
+    }
+
+    #[test]
+    fn credentials_without_separator_are_rejected() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode("ursula");
+        let headers = headers_with_authorization(&format!("Basic {}", encoded));
+
+        assert_err!(basic_authentication(&headers));
+    }
+}
@@ -0,0 +1,5 @@
+mod middleware;
+mod password;
+
+pub use middleware::*;
+pub use password::*;
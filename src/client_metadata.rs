@@ -0,0 +1,40 @@
+use actix_web::dev::Payload;
+use actix_web::http::header::{REFERER, USER_AGENT};
+use actix_web::{FromRequest, HttpRequest};
+use std::convert::Infallible;
+use std::future::{ready, Ready};
+
+/// Who is on the other end of a request, as far as we can tell. Recorded
+/// next to the changes a request makes to a subscription.
+///
+/// The address comes from the `Forwarded`/`X-Forwarded-For` headers when
+/// present, since the application runs behind a proxy in production. Clients
+/// can set those headers themselves, so treat it as informative only.
+#[derive(Clone, Debug, Default)]
+pub struct ClientMetadata {
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// The page the request came from, if the client says.
+    pub referer: Option<String>,
+}
+
+impl FromRequest for ClientMetadata {
+    type Error = Infallible;
+    type Future = Ready<Result<ClientMetadata, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        let ip_address = req.connection_info().realip_remote_addr().map(Into::into);
+        let header = |name| {
+            req.headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(Into::into)
+        };
+
+        ready(Ok(ClientMetadata {
+            ip_address,
+            user_agent: header(USER_AGENT),
+            referer: header(REFERER),
+        }))
+    }
+}
@@ -1,10 +1,20 @@
-use crate::domain::SubscriberEmail;
+use crate::action_links::ActionLinkSigner;
+use crate::domain::{SubscriberEmail, SubscriberEmailError};
+use crate::email_client::{
+    BrevoEmailClient, CaptureEmailClient, EmailSender, Mailbox, MeteredEmailSender, RetryPolicy,
+    SmtpEmailClient, TokenBucket,
+};
 use config::{Config, ConfigError, File};
+use lettre::transport::smtp::authentication::{Credentials, Mechanism};
+use lettre::transport::smtp::PoolConfig;
+use lettre::{AsyncSmtpTransport, Tokio1Executor};
 use secrecy::ExposeSecret;
 use secrecy::Secret;
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
 use sqlx::ConnectOptions;
+use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 #[derive(serde::Deserialize, Clone)]
@@ -12,6 +22,10 @@ pub struct Settings {
     pub database_settings: DatabaseSettings,
     pub application_settings: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub action_links: ActionLinkSettings,
+    pub health_check: HealthCheckSettings,
+    #[serde(skip)]
+    pub environment: Environment,
 }
 
 impl Settings {
@@ -30,10 +44,119 @@ impl Settings {
             .add_source(config::Environment::with_prefix("APP").separator("__"))
             .build()?;
 
-        settings.try_deserialize()
+        let mut settings: Self = settings.try_deserialize()?;
+        settings.environment = environment;
+        settings.validate()?;
+
+        Ok(settings)
+    }
+
+    /// Catches values that deserialize fine but would only fail, or panic,
+    /// once the application runs.
+    fn validate(&self) -> Result<(), ConfigError> {
+        self.application_settings.validate()?;
+        self.action_links.validate()?;
+        if let Some(rate_limit) = &self.email_client.rate_limit {
+            rate_limit.validate()?;
+        }
+
+        Ok(())
     }
 }
 
+/// Keys for signing stateless action links. To rotate, add a new key, make it
+/// current, and drop the old one once the links it signed have expired.
+#[derive(serde::Deserialize, Clone)]
+pub struct ActionLinkSettings {
+    pub current_key_id: String,
+    pub signing_keys: HashMap<String, Secret<String>>,
+    pub unsubscribe_link_ttl_seconds: u64,
+}
+
+impl ActionLinkSettings {
+    /// Links are signed with HMAC-SHA256, whose keys should be at least as
+    /// long as its 32-byte output.
+    pub const MIN_SIGNING_KEY_LENGTH: usize = 32;
+
+    /// Key ids are unique by construction, being map keys; their secrets must
+    /// be too, or rotating to a new id would not rotate anything.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !self.signing_keys.contains_key(&self.current_key_id) {
+            return Err(ConfigError::Message(format!(
+                "action_links.current_key_id `{}` is not among action_links.signing_keys.",
+                self.current_key_id
+            )));
+        }
+        let mut key_ids: Vec<&String> = self.signing_keys.keys().collect();
+        key_ids.sort();
+        for (i, key_id) in key_ids.iter().enumerate() {
+            let key = self.signing_keys[*key_id].expose_secret();
+            if key_id.is_empty() {
+                return Err(ConfigError::Message(
+                    "action_links.signing_keys must not have an empty key id.".into(),
+                ));
+            }
+            if key.len() < Self::MIN_SIGNING_KEY_LENGTH {
+                return Err(ConfigError::Message(format!(
+                    "action_links.signing_keys.{} must be at least {} bytes long, got {}.",
+                    key_id,
+                    Self::MIN_SIGNING_KEY_LENGTH,
+                    key.len()
+                )));
+            }
+            if let Some(other_id) = key_ids[i + 1..]
+                .iter()
+                .find(|other_id| self.signing_keys[**other_id].expose_secret() == key)
+            {
+                return Err(ConfigError::Message(format!(
+                    "action_links.signing_keys.{} and action_links.signing_keys.{} share a secret.",
+                    key_id, other_id
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn signer(&self) -> ActionLinkSigner {
+        ActionLinkSigner::new(self.current_key_id.clone(), self.signing_keys.clone())
+            .expect("Action link settings are validated when they are loaded.")
+    }
+
+    /// How long the unsubscribe links in newsletter issues stay valid.
+    pub fn unsubscribe_link_ttl(&self) -> Duration {
+        Duration::from_secs(self.unsubscribe_link_ttl_seconds)
+    }
+}
+
+/// What `/health/ready` probes. Postgres is always checked; the email
+/// provider only when asked to, since an outage there leaves most of the
+/// application usable.
+#[derive(serde::Deserialize, Clone)]
+pub struct HealthCheckSettings {
+    pub timeout_milliseconds: u64,
+    #[serde(default)]
+    pub email_client: DependencyCheck,
+}
+
+impl HealthCheckSettings {
+    /// How long each probe may take before its dependency is reported down.
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_milliseconds)
+    }
+}
+
+/// `Optional` dependencies are reported on but never make the application
+/// unready; `Required` ones do.
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyCheck {
+    #[default]
+    Skip,
+    Optional,
+    Required,
+}
+
 #[derive(serde::Deserialize, Clone)]
 pub struct DatabaseSettings {
     pub username: String,
@@ -76,18 +199,286 @@ pub struct ApplicationSettings {
     pub port: u16,
     pub host: String,
     pub base_url: String,
+    pub hmac_secret: Secret<String>,
+    pub session_idle_timeout_seconds: u64,
+    pub subscription_token_ttl_seconds: u64,
+    /// Number of alphanumeric characters in confirmation tokens, each worth a
+    /// little under 6 bits of entropy.
+    pub subscription_token_length: usize,
+    /// Recorded with every consent, to tell which policy the subscriber
+    /// agreed to.
+    pub privacy_policy_version: String,
+    /// Serves a Swagger UI for the API at `/api/v1/docs`. Meant for local
+    /// development only.
+    #[serde(default)]
+    pub swagger_ui: bool,
+    /// Token Prometheus must send to scrape `/metrics`. Without one, the
+    /// endpoint is not served at all.
+    pub metrics_bearer_token: Option<Secret<String>>,
+}
+
+impl ApplicationSettings {
+    /// Cookies are signed with a key made from `hmac_secret`, which needs at
+    /// least 64 bytes.
+    pub const MIN_HMAC_SECRET_LENGTH: usize = 64;
+
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let length = self.hmac_secret.expose_secret().len();
+        if length < Self::MIN_HMAC_SECRET_LENGTH {
+            return Err(ConfigError::Message(format!(
+                "application_settings.hmac_secret must be at least {} bytes long, got {}.",
+                Self::MIN_HMAC_SECRET_LENGTH,
+                length
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn session_idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.session_idle_timeout_seconds)
+    }
+
+    /// How long a confirmation link stays valid.
+    pub fn subscription_token_ttl(&self) -> Duration {
+        Duration::from_secs(self.subscription_token_ttl_seconds)
+    }
 }
 
 #[derive(serde::Deserialize, Clone)]
 pub struct EmailClientSettings {
+    #[serde(default)]
+    pub kind: EmailBackendKind,
     pub base_url: String,
     pub sender_email: String,
+    pub sender_name: Option<String>,
     pub api_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub retry: RetrySettings,
+    pub rate_limit: Option<RateLimitSettings>,
+    pub smtp: Option<SmtpSettings>,
+    pub capture: Option<CaptureSettings>,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct RateLimitSettings {
+    pub sends_per_second: f64,
+    pub burst: u32,
+}
+
+impl RateLimitSettings {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(self.sends_per_second.is_finite() && self.sends_per_second > 0.0) {
+            return Err(ConfigError::Message(format!(
+                "email_client.rate_limit.sends_per_second must be a positive number, got {}.",
+                self.sends_per_second
+            )));
+        }
+        if self.burst == 0 {
+            return Err(ConfigError::Message(
+                "email_client.rate_limit.burst must be at least 1.".into(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn token_bucket(&self) -> TokenBucket {
+        TokenBucket::new(self.sends_per_second, self.burst)
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailBackendKind {
+    #[default]
+    Brevo,
+    Smtp,
+    Capture,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct CaptureSettings {
+    pub directory: String,
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    #[serde(default = "default_smtp_authentication")]
+    pub authentication: Vec<SmtpAuthMechanism>,
+    pub pool_max_size: u32,
+}
+
+/// How the connection to the relay is secured. `Implicit` is TLS from the
+/// first byte (usually port 465), `Starttls` upgrades a plain connection
+/// (usually port 587) and `None` must only be used against local servers.
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    None,
+    Starttls,
+    Implicit,
+}
+
+#[derive(serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpAuthMechanism {
+    Plain,
+    Login,
+}
+
+impl From<SmtpAuthMechanism> for Mechanism {
+    fn from(mechanism: SmtpAuthMechanism) -> Self {
+        match mechanism {
+            SmtpAuthMechanism::Plain => Mechanism::Plain,
+            SmtpAuthMechanism::Login => Mechanism::Login,
+        }
+    }
+}
+
+fn default_smtp_authentication() -> Vec<SmtpAuthMechanism> {
+    vec![SmtpAuthMechanism::Plain, SmtpAuthMechanism::Login]
+}
+
+impl SmtpSettings {
+    pub fn transport(
+        &self,
+        timeout: Duration,
+    ) -> Result<AsyncSmtpTransport<Tokio1Executor>, lettre::transport::smtp::Error> {
+        let builder = match self.tls {
+            SmtpTls::Implicit => AsyncSmtpTransport::<Tokio1Executor>::relay(&self.host)?,
+            SmtpTls::Starttls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&self.host)?,
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&self.host),
+        };
+        let mut builder = builder
+            .port(self.port)
+            .timeout(Some(timeout))
+            .pool_config(PoolConfig::new().max_size(self.pool_max_size))
+            .authentication(
+                self.authentication
+                    .iter()
+                    .copied()
+                    .map(Into::into)
+                    .collect(),
+            );
+
+        if let (Some(username), Some(password)) = (&self.username, &self.password) {
+            builder = builder.credentials(Credentials::new(
+                username.clone(),
+                password.expose_secret().clone(),
+            ));
+        }
+
+        Ok(builder.build())
+    }
+}
+
+#[derive(serde::Deserialize, Clone)]
+pub struct RetrySettings {
+    pub max_attempts: u32,
+    pub base_delay_milliseconds: u64,
+    pub max_delay_milliseconds: u64,
+    pub jitter: bool,
+}
+
+impl RetrySettings {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts.max(1),
+            base_delay: Duration::from_millis(self.base_delay_milliseconds),
+            max_delay: Duration::from_millis(self.max_delay_milliseconds),
+            jitter: self.jitter,
+        }
+    }
 }
 
 impl EmailClientSettings {
-    pub fn sender(&self) -> Result<SubscriberEmail, String> {
+    pub fn client(self) -> Arc<dyn EmailSender> {
+        match self.kind {
+            EmailBackendKind::Brevo => {
+                Arc::new(MeteredEmailSender::new(self.brevo_client(), "brevo"))
+            }
+            EmailBackendKind::Smtp => Arc::new(MeteredEmailSender::new(self.smtp_client(), "smtp")),
+            EmailBackendKind::Capture => {
+                Arc::new(MeteredEmailSender::new(self.capture_client(), "capture"))
+            }
+        }
+    }
+
+    /// The mailbox captured emails end up in, if the capture backend is in use.
+    pub fn mailbox(&self) -> Option<Mailbox> {
+        match self.kind {
+            EmailBackendKind::Capture => self
+                .capture
+                .as_ref()
+                .map(|capture| Mailbox::new(&capture.directory)),
+            _ => None,
+        }
+    }
+
+    fn brevo_client(self) -> BrevoEmailClient {
+        let sender_email = self.sender().expect("Invalid sender email address");
+        let timeout = self.timeout();
+        let retry_policy = self.retry.policy();
+        let send_rate = self
+            .rate_limit
+            .as_ref()
+            .map(RateLimitSettings::token_bucket);
+
+        let client = BrevoEmailClient::new(
+            self.base_url,
+            sender_email,
+            self.api_token,
+            timeout,
+            retry_policy,
+            send_rate,
+        );
+        match self.sender_name {
+            Some(sender_name) => client.with_sender_name(sender_name),
+            None => client,
+        }
+    }
+
+    fn smtp_client(self) -> SmtpEmailClient {
+        let sender_email = self.sender().expect("Invalid sender email address");
+        let transport = self
+            .smtp
+            .as_ref()
+            .expect("The smtp email backend requires `email_client.smtp` settings")
+            .transport(self.timeout())
+            .expect("Failed to build the SMTP transport");
+        let send_rate = self
+            .rate_limit
+            .as_ref()
+            .map(RateLimitSettings::token_bucket);
+
+        let client = SmtpEmailClient::new(transport, sender_email, self.retry.policy(), send_rate);
+        match self.sender_name {
+            Some(sender_name) => client.with_sender_name(sender_name),
+            None => client,
+        }
+    }
+
+    fn capture_client(self) -> CaptureEmailClient {
+        let sender_email = self.sender().expect("Invalid sender email address");
+        let mailbox = self
+            .mailbox()
+            .expect("The capture email backend requires `email_client.capture` settings");
+
+        let client = CaptureEmailClient::new(sender_email, mailbox);
+        match self.sender_name {
+            Some(sender_name) => client.with_sender_name(sender_name),
+            None => client,
+        }
+    }
+
+    pub fn sender(&self) -> Result<SubscriberEmail, SubscriberEmailError> {
         SubscriberEmail::parse(self.sender_email.clone())
     }
 
@@ -96,7 +487,9 @@ impl EmailClientSettings {
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Environment {
+    #[default]
     Local,
     Production,
 }
@@ -124,3 +517,99 @@ impl TryFrom<String> for Environment {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::configuration::{ActionLinkSettings, ApplicationSettings, RateLimitSettings};
+    use claim::{assert_err, assert_ok};
+    use secrecy::Secret;
+
+    fn application_settings(hmac_secret: &str) -> ApplicationSettings {
+        ApplicationSettings {
+            port: 8000,
+            host: "127.0.0.1".into(),
+            base_url: "http://127.0.0.1".into(),
+            hmac_secret: Secret::new(hmac_secret.into()),
+            session_idle_timeout_seconds: 1800,
+            subscription_token_ttl_seconds: 86400,
+            subscription_token_length: 32,
+            privacy_policy_version: "2023-11-01".into(),
+            swagger_ui: false,
+            metrics_bearer_token: None,
+        }
+    }
+
+    fn action_links(current_key_id: &str, signing_keys: &[(&str, &str)]) -> ActionLinkSettings {
+        ActionLinkSettings {
+            current_key_id: current_key_id.into(),
+            signing_keys: signing_keys
+                .iter()
+                .map(|(id, key)| (id.to_string(), Secret::new(key.to_string())))
+                .collect(),
+            unsubscribe_link_ttl_seconds: 86400,
+        }
+    }
+
+    fn rate_limit(sends_per_second: f64, burst: u32) -> RateLimitSettings {
+        RateLimitSettings {
+            sends_per_second,
+            burst,
+        }
+    }
+
+    #[test]
+    fn an_hmac_secret_of_64_bytes_is_accepted() {
+        assert_ok!(application_settings(&"a".repeat(64)).validate());
+    }
+
+    #[test]
+    fn a_shorter_hmac_secret_is_rejected() {
+        assert_err!(application_settings(&"a".repeat(63)).validate());
+    }
+
+    #[test]
+    fn distinct_signing_keys_of_32_bytes_are_accepted() {
+        let (old, new) = ("a".repeat(32), "b".repeat(32));
+        assert_ok!(action_links("new", &[("old", &old), ("new", &new)]).validate());
+    }
+
+    #[test]
+    fn the_current_signing_key_must_be_configured() {
+        assert_err!(action_links("new", &[]).validate());
+        assert_err!(action_links("new", &[("old", &"a".repeat(32))]).validate());
+    }
+
+    #[test]
+    fn a_shorter_signing_key_is_rejected() {
+        assert_err!(action_links("new", &[("new", &"a".repeat(31))]).validate());
+    }
+
+    #[test]
+    fn signing_keys_sharing_a_secret_are_rejected() {
+        let key = "a".repeat(32);
+        assert_err!(action_links("new", &[("old", &key), ("new", &key)]).validate());
+    }
+
+    #[test]
+    fn an_empty_signing_key_id_is_rejected() {
+        let (old, new) = ("a".repeat(32), "b".repeat(32));
+        assert_err!(action_links("new", &[("", &old), ("new", &new)]).validate());
+    }
+
+    #[test]
+    fn a_positive_rate_and_burst_are_accepted() {
+        assert_ok!(rate_limit(0.5, 1).validate());
+    }
+
+    #[test]
+    fn a_rate_that_is_not_positive_is_rejected() {
+        for sends_per_second in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert_err!(rate_limit(sends_per_second, 10).validate());
+        }
+    }
+
+    #[test]
+    fn an_empty_burst_is_rejected() {
+        assert_err!(rate_limit(10.0, 0).validate());
+    }
+}
@@ -1,7 +1,11 @@
 mod new_subscriber;
 mod subscriber_email;
 mod subscriber_name;
+mod subscription_actor;
+mod subscription_status;
 
 pub use new_subscriber::NewSubscriber;
-pub use subscriber_email::SubscriberEmail;
-pub use subscriber_name::SubscriberName;
+pub use subscriber_email::{SubscriberEmail, SubscriberEmailError};
+pub use subscriber_name::{SubscriberName, SubscriberNameError};
+pub use subscription_actor::SubscriptionActor;
+pub use subscription_status::{InvalidStatusTransition, SubscriptionStatus};
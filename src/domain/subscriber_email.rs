@@ -3,13 +3,34 @@ use validator::validate_email;
 #[derive(Debug, Clone)]
 pub struct SubscriberEmail(String);
 
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SubscriberEmailError {
+    #[error("The email address is empty.")]
+    Empty,
+    #[error("{0} is not a valid email address.")]
+    Invalid(String),
+}
+
+impl SubscriberEmailError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "email.empty",
+            Self::Invalid(_) => "email.invalid",
+        }
+    }
+}
+
 impl SubscriberEmail {
-    pub fn parse(email: String) -> Result<SubscriberEmail, String> {
-        if validate_email(&email) {
-            return Ok(Self(email));
+    pub fn parse(email: String) -> Result<SubscriberEmail, SubscriberEmailError> {
+        if email.trim().is_empty() {
+            return Err(SubscriberEmailError::Empty);
+        }
+        if !validate_email(&email) {
+            return Err(SubscriberEmailError::Invalid(email));
         }
 
-        Err(format!("{} is not valid email!", email))
+        Ok(Self(email))
     }
 }
 
@@ -21,7 +42,7 @@ impl AsRef<str> for SubscriberEmail {
 
 #[cfg(test)]
 mod tests {
-    use crate::domain::SubscriberEmail;
+    use crate::domain::{SubscriberEmail, SubscriberEmailError};
     use claim::assert_err;
     use fake::faker::internet::en::SafeEmail;
     use fake::Fake;
@@ -56,14 +77,20 @@ mod tests {
     fn empty_string_is_rejected() {
         let email = "".to_string();
 
-        assert_err!(SubscriberEmail::parse(email));
+        assert_eq!(
+            SubscriberEmailError::Empty,
+            SubscriberEmail::parse(email).unwrap_err()
+        );
     }
 
     #[test]
     fn email_missing_at_symbol_is_rejected() {
         let email = "invalidEmail.com".to_string();
 
-        assert_err!(SubscriberEmail::parse(email));
+        assert_eq!(
+            SubscriberEmailError::Invalid(email.clone()),
+            SubscriberEmail::parse(email).unwrap_err()
+        );
     }
 
     #[test]
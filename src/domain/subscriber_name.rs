@@ -1,22 +1,45 @@
 use unicode_segmentation::UnicodeSegmentation;
 
+const MAX_GRAPHEMES: usize = 256;
+const FORBIDDEN_CHARACTERS: [char; 9] = ['/', '(', ')', '{', '}', '"', '<', '>', '\\'];
+
 #[derive(Debug)]
 pub struct SubscriberName(String);
 
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum SubscriberNameError {
+    #[error("The subscriber name is empty.")]
+    Empty,
+    #[error("The subscriber name is longer than {MAX_GRAPHEMES} characters.")]
+    TooLong,
+    #[error("The subscriber name contains the forbidden character {0:?}.")]
+    ForbiddenCharacter(char),
+}
+
+impl SubscriberNameError {
+    /// A stable, machine-readable name for the error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Empty => "name.empty",
+            Self::TooLong => "name.too_long",
+            Self::ForbiddenCharacter(_) => "name.forbidden_character",
+        }
+    }
+}
+
 impl SubscriberName {
-    pub fn parse(name: String) -> Result<SubscriberName, String> {
-        let is_empty_or_whitespace = name.trim().is_empty();
-        let is_to_long = name.graphemes(true).count() > 256;
-        let forbidden_characters = ['/', '(', ')', '{', '}', '"', '<', '>', '\\'];
-        let is_contains_forbidden_characters = name
-            .chars()
-            .any(|char| forbidden_characters.contains(&char));
-
-        if is_empty_or_whitespace || is_to_long || is_contains_forbidden_characters {
-            Err(format!("{} is not valid subscriber name", name))
-        } else {
-            Ok(Self(name))
+    pub fn parse(name: String) -> Result<SubscriberName, SubscriberNameError> {
+        if name.trim().is_empty() {
+            return Err(SubscriberNameError::Empty);
+        }
+        if name.graphemes(true).count() > MAX_GRAPHEMES {
+            return Err(SubscriberNameError::TooLong);
+        }
+        if let Some(forbidden) = name.chars().find(|c| FORBIDDEN_CHARACTERS.contains(c)) {
+            return Err(SubscriberNameError::ForbiddenCharacter(forbidden));
         }
+
+        Ok(Self(name))
     }
 }
 
@@ -28,7 +51,7 @@ impl AsRef<str> for SubscriberName {
 
 #[cfg(test)]
 mod tests {
-    use crate::domain::SubscriberName;
+    use crate::domain::{SubscriberName, SubscriberNameError};
     use claim::{assert_err, assert_ok};
 
     #[test]
@@ -42,14 +65,20 @@ mod tests {
     fn a_257_grapheme_name_is_rejected() {
         let name = "a".repeat(257);
 
-        assert_err!(SubscriberName::parse(name));
+        assert_eq!(
+            SubscriberNameError::TooLong,
+            SubscriberName::parse(name).unwrap_err()
+        );
     }
 
     #[test]
     fn whitespace_only_name_is_rejected() {
         let name = "      ".to_string();
 
-        assert_err!(SubscriberName::parse(name));
+        assert_eq!(
+            SubscriberNameError::Empty,
+            SubscriberName::parse(name).unwrap_err()
+        );
     }
 
     #[test]
@@ -62,7 +91,10 @@ mod tests {
     #[test]
     fn name_with_forbidden_characters_is_rejected() {
         for name in ['/', '(', ')', '{', '}', '"', '<', '>', '\\'] {
-            assert_err!(SubscriberName::parse(name.to_string()));
+            assert_eq!(
+                SubscriberNameError::ForbiddenCharacter(name),
+                SubscriberName::parse(format!("Ursula {}", name)).unwrap_err()
+            );
         }
     }
 
@@ -0,0 +1,19 @@
+/// Who caused a change to a subscription. Stored as the `subscription_actor`
+/// Postgres enum.
+#[derive(sqlx::Type, Clone, Copy, Debug, PartialEq, Eq)]
+#[sqlx(type_name = "subscription_actor", rename_all = "snake_case")]
+pub enum SubscriptionActor {
+    Subscriber,
+    Admin,
+    System,
+}
+
+impl SubscriptionActor {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Subscriber => "subscriber",
+            Self::Admin => "admin",
+            Self::System => "system",
+        }
+    }
+}
@@ -0,0 +1,124 @@
+/// Where a subscription stands. Stored as the `subscription_status` Postgres
+/// enum.
+#[derive(
+    sqlx::Type,
+    serde::Serialize,
+    serde::Deserialize,
+    utoipa::ToSchema,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+)]
+#[sqlx(type_name = "subscription_status", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SubscriptionStatus {
+    PendingConfirmation,
+    Confirmed,
+    Unsubscribed,
+    Bounced,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+#[error("A subscription cannot go from {from:?} to {to:?}.")]
+pub struct InvalidStatusTransition {
+    pub from: SubscriptionStatus,
+    pub to: SubscriptionStatus,
+}
+
+impl SubscriptionStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::PendingConfirmation => "pending_confirmation",
+            Self::Confirmed => "confirmed",
+            Self::Unsubscribed => "unsubscribed",
+            Self::Bounced => "bounced",
+        }
+    }
+
+    /// Checks `next` against the allowed transitions. Staying in the same
+    /// status is always allowed, so repeated requests are harmless.
+    ///
+    /// - a pending subscription is confirmed, or ends when the subscriber
+    ///   unsubscribes or the address bounces;
+    /// - a confirmed subscription ends the same two ways;
+    /// - an ended subscription starts over as pending when the address
+    ///   subscribes again, or moves from bounced to unsubscribed.
+    pub fn transition_to(self, next: Self) -> Result<Self, InvalidStatusTransition> {
+        use SubscriptionStatus::*;
+
+        let allowed = self == next
+            || matches!(
+                (self, next),
+                (PendingConfirmation, Confirmed | Unsubscribed | Bounced)
+                    | (Confirmed, Unsubscribed | Bounced)
+                    | (Unsubscribed | Bounced, PendingConfirmation)
+                    | (Bounced, Unsubscribed)
+            );
+
+        if allowed {
+            Ok(next)
+        } else {
+            Err(InvalidStatusTransition {
+                from: self,
+                to: next,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::SubscriptionStatus::*;
+    use crate::domain::{InvalidStatusTransition, SubscriptionStatus};
+    use claim::{assert_err, assert_ok};
+
+    const ALL: [SubscriptionStatus; 4] = [PendingConfirmation, Confirmed, Unsubscribed, Bounced];
+
+    #[test]
+    fn staying_in_the_same_status_is_allowed() {
+        for status in ALL {
+            assert_eq!(Ok(status), status.transition_to(status));
+        }
+    }
+
+    #[test]
+    fn a_pending_subscription_can_be_confirmed() {
+        assert_ok!(PendingConfirmation.transition_to(Confirmed));
+    }
+
+    #[test]
+    fn every_subscription_can_be_ended() {
+        for status in ALL {
+            assert_ok!(status.transition_to(Unsubscribed));
+        }
+        for status in [PendingConfirmation, Confirmed] {
+            assert_ok!(status.transition_to(Bounced));
+        }
+    }
+
+    #[test]
+    fn an_ended_subscription_starts_over_as_pending() {
+        assert_ok!(Unsubscribed.transition_to(PendingConfirmation));
+        assert_ok!(Bounced.transition_to(PendingConfirmation));
+    }
+
+    #[test]
+    fn an_ended_subscription_cannot_be_confirmed_directly() {
+        assert_eq!(
+            Err(InvalidStatusTransition {
+                from: Unsubscribed,
+                to: Confirmed
+            }),
+            Unsubscribed.transition_to(Confirmed)
+        );
+        assert_err!(Bounced.transition_to(Confirmed));
+    }
+
+    #[test]
+    fn a_confirmed_subscription_cannot_go_back_to_pending() {
+        assert_err!(Confirmed.transition_to(PendingConfirmation));
+        assert_err!(Unsubscribed.transition_to(Bounced));
+    }
+}
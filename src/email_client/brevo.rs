@@ -0,0 +1,496 @@
+use crate::domain::SubscriberEmail;
+use crate::email_client::{
+    send_with_retries, Email, EmailClientError, EmailSender, RetryPolicy, TokenBucket,
+};
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, Response, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Delivers emails through Brevo's transactional `/v3/smtp/email` REST API.
+#[derive(Clone)]
+pub struct BrevoEmailClient {
+    http_client: Client,
+    base_url: String,
+    sender: SubscriberEmail,
+    sender_name: Option<String>,
+    api_token: Secret<String>,
+    retry_policy: RetryPolicy,
+    send_rate: Option<Arc<TokenBucket>>,
+}
+
+impl BrevoEmailClient {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        api_token: Secret<String>,
+        timeout: std::time::Duration,
+        retry_policy: RetryPolicy,
+        send_rate: Option<TokenBucket>,
+    ) -> Self {
+        Self {
+            http_client: Client::builder().timeout(timeout).build().unwrap(),
+            base_url,
+            sender,
+            sender_name: None,
+            api_token,
+            retry_policy,
+            send_rate: send_rate.map(Arc::new),
+        }
+    }
+
+    /// The display name used unless an email sets its own.
+    pub fn with_sender_name(mut self, sender_name: impl Into<String>) -> Self {
+        self.sender_name = Some(sender_name.into());
+        self
+    }
+
+    async fn try_send(
+        &self,
+        url: &str,
+        request_body: &SendEmailRequest<'_>,
+    ) -> Result<(), EmailClientError> {
+        if let Some(send_rate) = &self.send_rate {
+            send_rate.acquire().await;
+        }
+
+        let response = self
+            .http_client
+            .post(url)
+            .header("api-key", self.api_token.expose_secret())
+            .json(request_body)
+            .send()
+            .await?;
+
+        if response.status() == StatusCode::TOO_MANY_REQUESTS {
+            return Err(EmailClientError::RateLimited {
+                retry_after: parse_retry_after(&response),
+            });
+        }
+        response.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailSender for BrevoEmailClient {
+    async fn send_email(&self, email: &Email) -> Result<(), EmailClientError> {
+        let url = format!("{}/v3/smtp/email", self.base_url);
+        let request_body = SendEmailRequest {
+            sender: SenderData {
+                email: self.sender.as_ref(),
+                name: email.sender_name.as_deref().or(self.sender_name.as_deref()),
+            },
+            to: vec![ReceiverData::from(&email.recipient)],
+            cc: email.cc.iter().map(ReceiverData::from).collect(),
+            bcc: email.bcc.iter().map(ReceiverData::from).collect(),
+            reply_to: email.reply_to.as_ref().map(ReceiverData::from),
+            subject: &email.subject,
+            html_content: &email.html_content,
+            text_content: &email.text_content,
+            tags: email.tags.iter().map(String::as_str).collect(),
+            headers: email
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect(),
+        };
+
+        send_with_retries(&self.retry_policy, || self.try_send(&url, &request_body)).await
+    }
+
+    /// Fetches the account details, which only succeeds with a valid API key.
+    async fn check_connection(&self) -> Result<(), EmailClientError> {
+        self.http_client
+            .get(format!("{}/v3/account", self.base_url))
+            .header("api-key", self.api_token.expose_secret())
+            .send()
+            .await?
+            .error_for_status()?;
+
+        Ok(())
+    }
+}
+
+/// `Retry-After` is either a number of seconds or an HTTP date.
+fn parse_retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = retry_at.with_timezone(&chrono::Utc) - chrono::Utc::now();
+
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SendEmailRequest<'a> {
+    sender: SenderData<'a>,
+    to: Vec<ReceiverData<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    cc: Vec<ReceiverData<'a>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    bcc: Vec<ReceiverData<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_to: Option<ReceiverData<'a>>,
+    subject: &'a str,
+    html_content: &'a str,
+    text_content: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<&'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<&'a str, &'a str>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SenderData<'a> {
+    email: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<&'a str>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReceiverData<'a> {
+    email: &'a str,
+}
+
+impl<'a> From<&'a SubscriberEmail> for ReceiverData<'a> {
+    fn from(email: &'a SubscriberEmail) -> Self {
+        Self {
+            email: email.as_ref(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{
+        BrevoEmailClient, Email, EmailClientError, EmailSender, RetryPolicy,
+    };
+    use claim::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use wiremock::matchers::{any, header, header_exists, method, path};
+    use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+
+            if let Ok(body) = result {
+                body.get("sender")
+                    .is_some_and(|value| value.get("email").is_some())
+                    && body.get("to").is_some_and(|value| value.is_array())
+                    && body.get("subject").is_some()
+                    && body.get("htmlContent").is_some()
+                    && body.get("textContent").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    fn get_email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn get_subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn get_content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn get_message() -> Email {
+        Email::new(get_email(), get_subject(), get_content(), get_content())
+    }
+
+    fn get_email_client(base_url: String) -> BrevoEmailClient {
+        get_email_client_with_retries(base_url, RetryPolicy::no_retries())
+    }
+
+    fn get_email_client_with_retries(
+        base_url: String,
+        retry_policy: RetryPolicy,
+    ) -> BrevoEmailClient {
+        BrevoEmailClient::new(
+            base_url,
+            get_email(),
+            Secret::new(Faker.fake()),
+            std::time::Duration::from_millis(200),
+            retry_policy,
+            None,
+        )
+    }
+
+    fn three_quick_attempts() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(10),
+            jitter: true,
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_fires_a_request_to_base_url() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client(mock_server.uri());
+
+        Mock::given(header_exists("api-key"))
+            .and(header("Content-Type", "application/json"))
+            .and(path("/v3/smtp/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client.send_email(&get_message()).await;
+    }
+
+    #[tokio::test]
+    async fn send_email_maps_every_message_option_onto_the_payload() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client(mock_server.uri()).with_sender_name("Default");
+        let (reply_to, cc, bcc) = (get_email(), get_email(), get_email());
+        let message = get_message()
+            .sender_name("Newsletter")
+            .reply_to(reply_to.clone())
+            .cc(cc.clone())
+            .bcc(bcc.clone())
+            .tag("newsletter")
+            .header("List-Unsubscribe", "<https://my-api.com/unsubscribe>");
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_ok!(email_client.send_email(&message).await);
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        assert_eq!("Newsletter", body["sender"]["name"]);
+        assert_eq!(reply_to.as_ref(), body["replyTo"]["email"]);
+        assert_eq!(cc.as_ref(), body["cc"][0]["email"]);
+        assert_eq!(bcc.as_ref(), body["bcc"][0]["email"]);
+        assert_eq!("newsletter", body["tags"][0]);
+        assert_eq!(
+            "<https://my-api.com/unsubscribe>",
+            body["headers"]["List-Unsubscribe"]
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_omits_unset_message_options() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_ok!(email_client.send_email(&get_message()).await);
+
+        let requests = mock_server.received_requests().await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&requests[0].body).unwrap();
+        for field in ["cc", "bcc", "replyTo", "tags", "headers"] {
+            assert!(body.get(field).is_none(), "unexpected `{}`", field);
+        }
+        assert!(body["sender"].get("name").is_none());
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_takes_to_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client(mock_server.uri());
+        let response = ResponseTemplate::new(500).set_delay(std::time::Duration::from_secs(180));
+
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_transient_server_errors() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client_with_retries(mock_server.uri(), three_quick_attempts());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_gives_up_after_max_attempts() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client_with_retries(mock_server.uri(), three_quick_attempts());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_when_throttled() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client_with_retries(mock_server.uri(), three_quick_attempts());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_does_not_retry_client_errors() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client_with_retries(mock_server.uri(), three_quick_attempts());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(400))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_honors_retry_after() {
+        let mock_server = MockServer::start().await;
+        let retry_policy = RetryPolicy {
+            max_delay: std::time::Duration::from_secs(2),
+            ..three_quick_attempts()
+        };
+        let email_client = get_email_client_with_retries(mock_server.uri(), retry_policy);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let start = std::time::Instant::now();
+        let outcome = email_client.send_email(&get_message()).await;
+
+        assert_ok!(outcome);
+        assert!(start.elapsed() >= std::time::Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn send_email_returns_rate_limited_when_retry_after_exceeds_max_delay() {
+        let mock_server = MockServer::start().await;
+        let email_client = get_email_client_with_retries(mock_server.uri(), three_quick_attempts());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "120"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email(&get_message()).await;
+
+        match assert_err!(outcome) {
+            EmailClientError::RateLimited { retry_after } => {
+                assert_eq!(Some(std::time::Duration::from_secs(120)), retry_after)
+            }
+            other => panic!("Expected a rate limited error, got {:?}", other),
+        }
+    }
+}
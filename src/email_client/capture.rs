@@ -0,0 +1,240 @@
+use crate::domain::SubscriberEmail;
+use crate::email_client::{Email, EmailClientError, EmailSender};
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// An email that was captured instead of being delivered.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct CapturedEmail {
+    pub id: Uuid,
+    pub recipient: String,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub headers: Vec<(String, String)>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// Captured emails, one JSON file per message. They live on disk rather than
+/// in memory so that they survive restarts, and so that the `/dev/mailbox`
+/// pages can read what the email client captured.
+#[derive(Clone, Debug)]
+pub struct Mailbox {
+    directory: PathBuf,
+}
+
+impl Mailbox {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self {
+            directory: directory.into(),
+        }
+    }
+
+    pub async fn store(&self, email: &CapturedEmail) -> Result<(), std::io::Error> {
+        tokio::fs::create_dir_all(&self.directory).await?;
+        let contents = serde_json::to_vec_pretty(email)?;
+        tokio::fs::write(self.path(email.id), contents).await
+    }
+
+    /// Returns every captured email, most recent first.
+    pub async fn list(&self) -> Result<Vec<CapturedEmail>, std::io::Error> {
+        let mut entries = match tokio::fs::read_dir(&self.directory).await {
+            Ok(entries) => entries,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(error) => return Err(error),
+        };
+
+        let mut emails = vec![];
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                let contents = tokio::fs::read(&path).await?;
+                emails.push(serde_json::from_slice(&contents)?);
+            }
+        }
+        emails.sort_by_key(|email: &CapturedEmail| std::cmp::Reverse(email.captured_at));
+
+        Ok(emails)
+    }
+
+    pub async fn get(&self, id: Uuid) -> Result<Option<CapturedEmail>, std::io::Error> {
+        match tokio::fs::read(self.path(id)).await {
+            Ok(contents) => Ok(Some(serde_json::from_slice(&contents)?)),
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn path(&self, id: Uuid) -> PathBuf {
+        self.directory.join(format!("{}.json", id))
+    }
+}
+
+/// Stores every outgoing email in a [`Mailbox`] instead of sending it, so the
+/// application can run locally without any email provider.
+#[derive(Clone)]
+pub struct CaptureEmailClient {
+    sender: SubscriberEmail,
+    sender_name: Option<String>,
+    mailbox: Mailbox,
+}
+
+impl CaptureEmailClient {
+    pub fn new(sender: SubscriberEmail, mailbox: Mailbox) -> Self {
+        Self {
+            sender,
+            sender_name: None,
+            mailbox,
+        }
+    }
+
+    /// The display name used unless an email sets its own.
+    pub fn with_sender_name(mut self, sender_name: impl Into<String>) -> Self {
+        self.sender_name = Some(sender_name.into());
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailSender for CaptureEmailClient {
+    async fn send_email(&self, email: &Email) -> Result<(), EmailClientError> {
+        let from = match email.sender_name.as_ref().or(self.sender_name.as_ref()) {
+            Some(name) => format!("{} <{}>", name, self.sender.as_ref()),
+            None => self.sender.as_ref().to_owned(),
+        };
+        let mut headers = vec![
+            ("From".to_owned(), from),
+            ("To".to_owned(), email.recipient.as_ref().to_owned()),
+        ];
+        for cc in &email.cc {
+            headers.push(("Cc".to_owned(), cc.as_ref().to_owned()));
+        }
+        for bcc in &email.bcc {
+            headers.push(("Bcc".to_owned(), bcc.as_ref().to_owned()));
+        }
+        if let Some(reply_to) = &email.reply_to {
+            headers.push(("Reply-To".to_owned(), reply_to.as_ref().to_owned()));
+        }
+        headers.push(("Subject".to_owned(), email.subject.clone()));
+        headers.extend(email.headers.iter().cloned());
+
+        let email = CapturedEmail {
+            id: Uuid::new_v4(),
+            recipient: email.recipient.as_ref().to_owned(),
+            subject: email.subject.clone(),
+            html_content: email.html_content.clone(),
+            text_content: email.text_content.clone(),
+            headers,
+            tags: email.tags.clone(),
+            captured_at: Utc::now(),
+        };
+
+        self.mailbox
+            .store(&email)
+            .await
+            .map_err(EmailClientError::Capture)
+    }
+
+    /// Captured emails can be stored as long as the mailbox directory exists.
+    async fn check_connection(&self) -> Result<(), EmailClientError> {
+        tokio::fs::create_dir_all(&self.mailbox.directory)
+            .await
+            .map_err(EmailClientError::Capture)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{CaptureEmailClient, Email, EmailSender, Mailbox};
+    use claim::{assert_none, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::Fake;
+    use uuid::Uuid;
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn mailbox() -> Mailbox {
+        Mailbox::new(std::env::temp_dir().join(Uuid::new_v4().to_string()))
+    }
+
+    #[tokio::test]
+    async fn an_empty_mailbox_lists_no_emails() {
+        let emails = assert_ok!(mailbox().list().await);
+
+        assert!(emails.is_empty());
+    }
+
+    #[tokio::test]
+    async fn sent_emails_are_captured_most_recent_first() {
+        let mailbox = mailbox();
+        let email_client = CaptureEmailClient::new(email(), mailbox.clone());
+        let recipient = email();
+
+        for subject in ["First", "Second"] {
+            assert_ok!(
+                email_client
+                    .send_email(&Email::new(recipient.clone(), subject, "<p>Hi</p>", "Hi"))
+                    .await
+            );
+        }
+
+        let emails = assert_ok!(mailbox.list().await);
+        assert_eq!(2, emails.len());
+        assert_eq!("Second", emails[0].subject);
+        assert_eq!(recipient.as_ref(), emails[1].recipient);
+        assert_eq!("<p>Hi</p>", emails[1].html_content);
+        assert_eq!("Hi", emails[1].text_content);
+
+        let email = assert_ok!(mailbox.get(emails[1].id).await).unwrap();
+        assert_eq!("First", email.subject);
+    }
+
+    #[tokio::test]
+    async fn message_options_are_captured_as_headers_and_tags() {
+        let mailbox = mailbox();
+        let sender = email();
+        let email_client =
+            CaptureEmailClient::new(sender.clone(), mailbox.clone()).with_sender_name("Default");
+        let reply_to = email();
+        let message = Email::new(email(), "Subject", "<p>Hi</p>", "Hi")
+            .sender_name("Newsletter")
+            .reply_to(reply_to.clone())
+            .tag("newsletter")
+            .header("List-Unsubscribe", "<https://my-api.com/unsubscribe>");
+
+        assert_ok!(email_client.send_email(&message).await);
+
+        let emails = assert_ok!(mailbox.list().await);
+        let headers = &emails[0].headers;
+        let header = |name: &str| {
+            headers
+                .iter()
+                .find(|(header, _)| header == name)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(
+            Some(format!("Newsletter <{}>", sender.as_ref()).as_str()),
+            header("From")
+        );
+        assert_eq!(Some(reply_to.as_ref()), header("Reply-To"));
+        assert_eq!(
+            Some("<https://my-api.com/unsubscribe>"),
+            header("List-Unsubscribe")
+        );
+        assert_eq!(vec!["newsletter".to_owned()], emails[0].tags);
+    }
+
+    #[tokio::test]
+    async fn get_returns_none_for_an_unknown_email() {
+        assert_none!(assert_ok!(mailbox().get(Uuid::new_v4()).await));
+    }
+}
@@ -0,0 +1,82 @@
+use crate::domain::SubscriberEmail;
+
+/// An outgoing email. Every backend maps it onto its own wire format, so
+/// anything set here reaches the recipient whichever provider is configured.
+///
+/// ```
+/// use zero2prod::domain::SubscriberEmail;
+/// use zero2prod::email_client::Email;
+///
+/// let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+/// let support = SubscriberEmail::parse("support@example.com".into()).unwrap();
+/// let email = Email::new(recipient, "Welcome!", "<p>Hi!</p>", "Hi!")
+///     .reply_to(support)
+///     .tag("confirmation")
+///     .header("List-Unsubscribe", "<https://example.com/unsubscribe>");
+/// ```
+#[derive(Clone, Debug)]
+pub struct Email {
+    pub recipient: SubscriberEmail,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub sender_name: Option<String>,
+    pub reply_to: Option<SubscriberEmail>,
+    pub cc: Vec<SubscriberEmail>,
+    pub bcc: Vec<SubscriberEmail>,
+    pub tags: Vec<String>,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Email {
+    pub fn new(
+        recipient: SubscriberEmail,
+        subject: impl Into<String>,
+        html_content: impl Into<String>,
+        text_content: impl Into<String>,
+    ) -> Self {
+        Self {
+            recipient,
+            subject: subject.into(),
+            html_content: html_content.into(),
+            text_content: text_content.into(),
+            sender_name: None,
+            reply_to: None,
+            cc: vec![],
+            bcc: vec![],
+            tags: vec![],
+            headers: vec![],
+        }
+    }
+
+    /// Overrides the sender display name configured on the email client.
+    pub fn sender_name(mut self, sender_name: impl Into<String>) -> Self {
+        self.sender_name = Some(sender_name.into());
+        self
+    }
+
+    pub fn reply_to(mut self, reply_to: SubscriberEmail) -> Self {
+        self.reply_to = Some(reply_to);
+        self
+    }
+
+    pub fn cc(mut self, cc: SubscriberEmail) -> Self {
+        self.cc.push(cc);
+        self
+    }
+
+    pub fn bcc(mut self, bcc: SubscriberEmail) -> Self {
+        self.bcc.push(bcc);
+        self
+    }
+
+    pub fn tag(mut self, tag: impl Into<String>) -> Self {
+        self.tags.push(tag.into());
+        self
+    }
+
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
@@ -0,0 +1,46 @@
+use std::time::Duration;
+
+#[derive(thiserror::Error, Debug)]
+pub enum EmailClientError {
+    #[error("The email provider is rate limiting us (retry after {retry_after:?}).")]
+    RateLimited { retry_after: Option<Duration> },
+    #[error(transparent)]
+    Request(#[from] reqwest::Error),
+    #[error(transparent)]
+    Smtp(#[from] lettre::transport::smtp::Error),
+    #[error("Failed to build the email message.")]
+    InvalidMessage(#[source] anyhow::Error),
+    #[error("Failed to capture the email.")]
+    Capture(#[source] std::io::Error),
+    #[error("The SMTP server did not answer our NOOP.")]
+    Unresponsive,
+}
+
+impl EmailClientError {
+    /// Only failures that may go away on their own are worth retrying:
+    /// timeouts, connection errors, throttling and server-side errors.
+    /// Any other 4xx means the request itself is wrong.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } | Self::Unresponsive => true,
+            Self::Request(error) => {
+                error.is_timeout()
+                    || error.is_connect()
+                    || error
+                        .status()
+                        .is_some_and(|status| status.is_server_error())
+            }
+            // 4xx SMTP replies are transient by definition; network and
+            // connection failures carry no reply code at all.
+            Self::Smtp(error) => {
+                error.is_transient()
+                    || error.is_timeout()
+                    || !(error.is_permanent()
+                        || error.is_client()
+                        || error.is_response()
+                        || error.is_tls())
+            }
+            Self::InvalidMessage(_) | Self::Capture(_) => false,
+        }
+    }
+}
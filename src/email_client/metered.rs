@@ -0,0 +1,40 @@
+use crate::email_client::{Email, EmailClientError, EmailSender};
+use crate::metrics::METRICS;
+
+/// Counts the emails another sender handles in `emails_sent_total`, by
+/// backend and outcome. Retries happen inside the wrapped sender, so each
+/// email is counted once.
+pub struct MeteredEmailSender<S> {
+    inner: S,
+    backend: &'static str,
+}
+
+impl<S> MeteredEmailSender<S> {
+    pub fn new(inner: S, backend: &'static str) -> Self {
+        Self { inner, backend }
+    }
+}
+
+#[async_trait::async_trait]
+impl<S: EmailSender> EmailSender for MeteredEmailSender<S> {
+    async fn send_email(&self, email: &Email) -> Result<(), EmailClientError> {
+        let outcome = self.inner.send_email(email).await;
+
+        let label = match &outcome {
+            Ok(()) => "sent",
+            Err(EmailClientError::RateLimited { .. }) => "rate_limited",
+            Err(error) if error.is_transient() => "transient_failure",
+            Err(_) => "permanent_failure",
+        };
+        METRICS
+            .emails_sent_total
+            .with_label_values(&[self.backend, label])
+            .inc();
+
+        outcome
+    }
+
+    async fn check_connection(&self) -> Result<(), EmailClientError> {
+        self.inner.check_connection().await
+    }
+}
@@ -0,0 +1,28 @@
+mod brevo;
+mod capture;
+mod email;
+mod error;
+mod metered;
+mod rate_limit;
+mod retry;
+mod smtp;
+
+pub use brevo::*;
+pub use capture::*;
+pub use email::*;
+pub use error::*;
+pub use metered::*;
+pub use rate_limit::*;
+pub use retry::*;
+pub use smtp::*;
+
+/// A way of delivering emails. Handlers only ever talk to this trait, so
+/// which provider is used is decided once, in `EmailClientSettings`.
+#[async_trait::async_trait]
+pub trait EmailSender: Send + Sync {
+    async fn send_email(&self, email: &Email) -> Result<(), EmailClientError>;
+
+    /// Checks that the provider can be reached with our credentials,
+    /// without sending anything.
+    async fn check_connection(&self) -> Result<(), EmailClientError>;
+}
@@ -0,0 +1,87 @@
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+/// Client-side token bucket keeping our send rate under the provider quota.
+///
+/// The bucket holds up to `burst` tokens and refills at `rate_per_second`;
+/// every send takes one token and waits for a refill when the bucket is empty.
+#[derive(Debug)]
+pub struct TokenBucket {
+    capacity: f64,
+    rate_per_second: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    pub fn new(rate_per_second: f64, burst: u32) -> Self {
+        let capacity = f64::from(burst.max(1));
+
+        Self {
+            capacity,
+            rate_per_second,
+            state: Mutex::new(BucketState {
+                tokens: capacity,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.last_refill).as_secs_f64();
+                state.tokens = (state.tokens + elapsed * self.rate_per_second).min(self.capacity);
+                state.last_refill = now;
+
+                if state.tokens >= 1.0 {
+                    state.tokens -= 1.0;
+                    return;
+                }
+
+                Duration::from_secs_f64((1.0 - state.tokens) / self.rate_per_second)
+            };
+
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::email_client::TokenBucket;
+    use std::time::Duration;
+    use tokio::time::Instant;
+
+    #[tokio::test(start_paused = true)]
+    async fn burst_is_served_without_waiting() {
+        let bucket = TokenBucket::new(1.0, 3);
+        let start = Instant::now();
+
+        for _ in 0..3 {
+            bucket.acquire().await;
+        }
+
+        assert!(start.elapsed() < Duration::from_millis(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_beyond_the_burst_wait_for_a_refill() {
+        let bucket = TokenBucket::new(2.0, 1);
+        let start = Instant::now();
+
+        bucket.acquire().await;
+        bucket.acquire().await;
+        bucket.acquire().await;
+
+        assert!(start.elapsed() >= Duration::from_millis(1000));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::email_client::EmailClientError;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+
+/// How many times, and how far apart, a failed send is retried.
+///
+/// Delays grow exponentially from `base_delay` and are capped at `max_delay`.
+/// With `jitter` enabled each delay is picked uniformly in `[0, delay]`, so
+/// that many clients failing at once do not retry in lockstep.
+#[derive(Clone, Debug)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    pub fn no_retries() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+            jitter: false,
+        }
+    }
+
+    /// Delay to wait after the given (1-based) failed attempt.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.pow(exponent))
+            .min(self.max_delay);
+
+        if self.jitter {
+            let millis = rand::thread_rng().gen_range(0..=delay.as_millis() as u64);
+            Duration::from_millis(millis)
+        } else {
+            delay
+        }
+    }
+}
+
+/// Runs `attempt_send` until it succeeds, fails with a permanent error or
+/// runs out of attempts, sleeping between attempts as the policy dictates.
+pub async fn send_with_retries<F, Fut>(
+    retry_policy: &RetryPolicy,
+    mut attempt_send: F,
+) -> Result<(), EmailClientError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<(), EmailClientError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match attempt_send().await {
+            Ok(()) => return Ok(()),
+            Err(error) if attempt < retry_policy.max_attempts && error.is_transient() => {
+                let delay = match error {
+                    EmailClientError::RateLimited {
+                        retry_after: Some(retry_after),
+                    } => {
+                        // Waiting longer than our own backoff cap would stall the
+                        // caller: hand the error back so it can reschedule instead.
+                        if retry_after > retry_policy.max_delay {
+                            return Err(error);
+                        }
+                        retry_after
+                    }
+                    _ => retry_policy.backoff(attempt),
+                };
+                tracing::warn!(
+                    "Attempt {} to send an email failed, retrying in {:?}: {:?}",
+                    attempt,
+                    delay,
+                    error
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(error) => return Err(error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::email_client::RetryPolicy;
+    use std::time::Duration;
+
+    fn policy(jitter: bool) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 10,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(1000),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn backoff_grows_exponentially() {
+        let policy = policy(false);
+
+        assert_eq!(Duration::from_millis(100), policy.backoff(1));
+        assert_eq!(Duration::from_millis(200), policy.backoff(2));
+        assert_eq!(Duration::from_millis(400), policy.backoff(3));
+    }
+
+    #[test]
+    fn backoff_is_capped_at_max_delay() {
+        let policy = policy(false);
+
+        assert_eq!(Duration::from_millis(1000), policy.backoff(5));
+        assert_eq!(Duration::from_millis(1000), policy.backoff(u32::MAX));
+    }
+
+    #[test]
+    fn jittered_backoff_never_exceeds_the_exponential_delay() {
+        let jittered = policy(true);
+        let exponential = policy(false);
+
+        for attempt in 1..10 {
+            assert!(jittered.backoff(attempt) <= exponential.backoff(attempt));
+        }
+    }
+}
@@ -0,0 +1,376 @@
+use crate::domain::SubscriberEmail;
+use crate::email_client::{
+    send_with_retries, Email, EmailClientError, EmailSender, RetryPolicy, TokenBucket,
+};
+use lettre::address::Envelope;
+use lettre::message::header::{HeaderName, HeaderValue, Headers};
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use std::sync::Arc;
+
+/// Delivers emails by relaying them through an SMTP server. TLS, credentials
+/// and connection pooling are all configured on the transport.
+#[derive(Clone)]
+pub struct SmtpEmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+    sender_name: Option<String>,
+    retry_policy: RetryPolicy,
+    send_rate: Option<Arc<TokenBucket>>,
+}
+
+impl SmtpEmailClient {
+    pub fn new(
+        transport: AsyncSmtpTransport<Tokio1Executor>,
+        sender: SubscriberEmail,
+        retry_policy: RetryPolicy,
+        send_rate: Option<TokenBucket>,
+    ) -> Self {
+        Self {
+            transport,
+            sender,
+            sender_name: None,
+            retry_policy,
+            send_rate: send_rate.map(Arc::new),
+        }
+    }
+
+    /// The display name used unless an email sets its own.
+    pub fn with_sender_name(mut self, sender_name: impl Into<String>) -> Self {
+        self.sender_name = Some(sender_name.into());
+        self
+    }
+
+    async fn try_send(&self, envelope: &Envelope, message: &[u8]) -> Result<(), EmailClientError> {
+        if let Some(send_rate) = &self.send_rate {
+            send_rate.acquire().await;
+        }
+
+        self.transport.send_raw(envelope, message).await?;
+
+        Ok(())
+    }
+
+    /// Builds a `multipart/alternative` message so that every client can pick
+    /// the richest body it is able to render.
+    fn build_message(&self, email: &Email) -> Result<(Envelope, Vec<u8>), EmailClientError> {
+        let sender_name = email.sender_name.clone().or(self.sender_name.clone());
+        let mut builder = Message::builder()
+            .from(mailbox(sender_name, &self.sender)?)
+            .to(mailbox(None, &email.recipient)?)
+            .subject(&email.subject);
+        if let Some(reply_to) = &email.reply_to {
+            builder = builder.reply_to(mailbox(None, reply_to)?);
+        }
+        for cc in &email.cc {
+            builder = builder.cc(mailbox(None, cc)?);
+        }
+        for bcc in &email.bcc {
+            builder = builder.bcc(mailbox(None, bcc)?);
+        }
+        let message = builder
+            .multipart(MultiPart::alternative_plain_html(
+                email.text_content.clone(),
+                email.html_content.clone(),
+            ))
+            .map_err(|error| EmailClientError::InvalidMessage(error.into()))?;
+
+        // lettre only knows about typed headers, so custom ones are encoded
+        // on their own and prepended to the formatted message. SMTP has no
+        // notion of tags: they travel in an `X-Tags` header.
+        let mut headers = Headers::new();
+        let custom_headers =
+            email.headers.iter().cloned().chain(
+                (!email.tags.is_empty()).then(|| ("X-Tags".to_owned(), email.tags.join(", "))),
+            );
+        for (name, value) in custom_headers {
+            let name = HeaderName::new_from_ascii(name)
+                .map_err(|error| EmailClientError::InvalidMessage(anyhow::anyhow!("{}", error)))?;
+            headers.insert_raw(HeaderValue::new(name, value));
+        }
+        let mut formatted = headers.to_string().into_bytes();
+        formatted.extend(message.formatted());
+
+        Ok((message.envelope().clone(), formatted))
+    }
+}
+
+#[async_trait::async_trait]
+impl EmailSender for SmtpEmailClient {
+    async fn send_email(&self, email: &Email) -> Result<(), EmailClientError> {
+        let (envelope, message) = self.build_message(email)?;
+
+        send_with_retries(&self.retry_policy, || self.try_send(&envelope, &message)).await
+    }
+
+    async fn check_connection(&self) -> Result<(), EmailClientError> {
+        if self.transport.test_connection().await? {
+            Ok(())
+        } else {
+            Err(EmailClientError::Unresponsive)
+        }
+    }
+}
+
+fn mailbox(name: Option<String>, email: &SubscriberEmail) -> Result<Mailbox, EmailClientError> {
+    let address = email
+        .as_ref()
+        .parse()
+        .map_err(|error: lettre::address::AddressError| {
+            EmailClientError::InvalidMessage(error.into())
+        })?;
+
+    Ok(Mailbox::new(name, address))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::domain::SubscriberEmail;
+    use crate::email_client::{Email, EmailClientError, EmailSender, RetryPolicy, SmtpEmailClient};
+    use base64::Engine;
+    use claim::{assert_err, assert_ok};
+    use fake::faker::internet::en::SafeEmail;
+    use fake::faker::lorem::en::{Paragraph, Sentence};
+    use fake::Fake;
+    use lettre::transport::smtp::authentication::Credentials;
+    use lettre::transport::smtp::PoolConfig;
+    use lettre::{AsyncSmtpTransport, Tokio1Executor};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    #[derive(Default)]
+    struct SinkState {
+        connections: usize,
+        auth_commands: Vec<String>,
+        rcpt_commands: usize,
+        messages: Vec<String>,
+    }
+
+    /// A minimal in-process SMTP server: it accepts every command it knows
+    /// about and records what it was sent.
+    struct SmtpSink {
+        port: u16,
+        state: Arc<Mutex<SinkState>>,
+    }
+
+    impl SmtpSink {
+        async fn start(rcpt_reply: &'static str) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            let state = Arc::new(Mutex::new(SinkState::default()));
+
+            let sink_state = state.clone();
+            tokio::spawn(async move {
+                loop {
+                    let (stream, _) = listener.accept().await.unwrap();
+                    sink_state.lock().unwrap().connections += 1;
+                    tokio::spawn(serve(stream, sink_state.clone(), rcpt_reply));
+                }
+            });
+
+            Self { port, state }
+        }
+
+        fn transport(&self) -> AsyncSmtpTransport<Tokio1Executor> {
+            AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1")
+                .port(self.port)
+                .timeout(Some(Duration::from_secs(2)))
+                .pool_config(PoolConfig::new().max_size(1))
+                .build()
+        }
+    }
+
+    async fn serve(
+        stream: tokio::net::TcpStream,
+        state: Arc<Mutex<SinkState>>,
+        rcpt_reply: &'static str,
+    ) {
+        let (reader, mut writer) = stream.into_split();
+        let mut lines = BufReader::new(reader).lines();
+        writer.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+
+        while let Ok(Some(line)) = lines.next_line().await {
+            let command = line.to_uppercase();
+            let reply: &str = if command.starts_with("EHLO") {
+                "250-sink\r\n250-AUTH PLAIN LOGIN\r\n250 8BITMIME\r\n"
+            } else if command.starts_with("AUTH") {
+                state.lock().unwrap().auth_commands.push(line.clone());
+                "235 Authentication succeeded\r\n"
+            } else if command.starts_with("RCPT") {
+                state.lock().unwrap().rcpt_commands += 1;
+                rcpt_reply
+            } else if command.starts_with("DATA") {
+                writer.write_all(b"354 Go ahead\r\n").await.unwrap();
+                let mut message = String::new();
+                while let Ok(Some(data_line)) = lines.next_line().await {
+                    if data_line == "." {
+                        break;
+                    }
+                    message.push_str(&data_line);
+                    message.push('\n');
+                }
+                state.lock().unwrap().messages.push(message);
+                "250 Queued\r\n"
+            } else if command.starts_with("QUIT") {
+                writer.write_all(b"221 Bye\r\n").await.unwrap();
+                return;
+            } else {
+                "250 OK\r\n"
+            };
+            writer.write_all(reply.as_bytes()).await.unwrap();
+        }
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn message() -> Email {
+        Email::new(email(), subject(), content(), content())
+    }
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(10),
+            jitter: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_delivers_a_multipart_alternative_message() {
+        let sink = SmtpSink::start("250 OK\r\n").await;
+        let email_client = SmtpEmailClient::new(sink.transport(), email(), retry_policy(), None);
+
+        let outcome = email_client
+            .send_email(&Email::new(email(), subject(), "<p>Hello!</p>", "Hello!"))
+            .await;
+
+        assert_ok!(outcome);
+        let state = sink.state.lock().unwrap();
+        assert_eq!(1, state.messages.len());
+        let message = &state.messages[0];
+        assert!(message.contains("multipart/alternative"));
+        assert!(message.contains("text/plain"));
+        assert!(message.contains("text/html"));
+    }
+
+    #[tokio::test]
+    async fn send_email_includes_message_options_as_headers() {
+        let sink = SmtpSink::start("250 OK\r\n").await;
+        let email_client = SmtpEmailClient::new(sink.transport(), email(), retry_policy(), None)
+            .with_sender_name("Newsletter");
+        let (reply_to, cc, bcc) = (email(), email(), email());
+        let message = message()
+            .reply_to(reply_to.clone())
+            .cc(cc.clone())
+            .bcc(bcc.clone())
+            .tag("newsletter")
+            .header("List-Unsubscribe", "<https://my-api.com/unsubscribe>");
+
+        assert_ok!(email_client.send_email(&message).await);
+
+        let state = sink.state.lock().unwrap();
+        let message = &state.messages[0];
+        assert!(message.contains("From: Newsletter <"));
+        assert!(message.contains(&format!("Reply-To: {}", reply_to.as_ref())));
+        assert!(message.contains(&format!("Cc: {}", cc.as_ref())));
+        assert!(!message.contains(bcc.as_ref()));
+        assert!(message.contains("List-Unsubscribe: <https://my-api.com/unsubscribe>"));
+        assert!(message.contains("X-Tags: newsletter"));
+        assert_eq!(3, state.rcpt_commands);
+    }
+
+    #[tokio::test]
+    async fn send_email_rejects_invalid_header_names() {
+        let sink = SmtpSink::start("250 OK\r\n").await;
+        let email_client = SmtpEmailClient::new(sink.transport(), email(), retry_policy(), None);
+
+        let outcome = email_client
+            .send_email(&message().header("Not a header", "value"))
+            .await;
+
+        assert!(matches!(
+            assert_err!(outcome),
+            EmailClientError::InvalidMessage(_)
+        ));
+        assert_eq!(0, sink.state.lock().unwrap().rcpt_commands);
+    }
+
+    #[tokio::test]
+    async fn send_email_authenticates_when_credentials_are_configured() {
+        let sink = SmtpSink::start("250 OK\r\n").await;
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous("127.0.0.1")
+            .port(sink.port)
+            .credentials(Credentials::new("ursula".into(), "secret".into()))
+            .build();
+        let email_client = SmtpEmailClient::new(transport, email(), retry_policy(), None);
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert_ok!(outcome);
+        // The pool warms up an idle connection of its own, so there may be
+        // more than one login: all of them must carry our credentials.
+        let expected = format!(
+            "AUTH PLAIN {}",
+            base64::engine::general_purpose::STANDARD.encode("\0ursula\0secret")
+        );
+        let state = sink.state.lock().unwrap();
+        assert!(!state.auth_commands.is_empty());
+        assert!(state
+            .auth_commands
+            .iter()
+            .all(|command| command == &expected));
+    }
+
+    #[tokio::test]
+    async fn send_email_does_not_retry_a_permanent_rejection() {
+        let sink = SmtpSink::start("550 No such user\r\n").await;
+        let email_client = SmtpEmailClient::new(sink.transport(), email(), retry_policy(), None);
+
+        let outcome = email_client.send_email(&message()).await;
+
+        let error = assert_err!(outcome);
+        assert!(matches!(error, EmailClientError::Smtp(_)));
+        assert!(!error.is_transient());
+        assert_eq!(1, sink.state.lock().unwrap().rcpt_commands);
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_a_transient_rejection() {
+        let sink = SmtpSink::start("451 Try again later\r\n").await;
+        let email_client = SmtpEmailClient::new(sink.transport(), email(), retry_policy(), None);
+
+        let outcome = email_client.send_email(&message()).await;
+
+        assert_err!(outcome);
+        assert_eq!(3, sink.state.lock().unwrap().rcpt_commands);
+    }
+
+    #[tokio::test]
+    async fn pooled_connections_are_reused_across_sends() {
+        let sink = SmtpSink::start("250 OK\r\n").await;
+        let email_client = SmtpEmailClient::new(sink.transport(), email(), retry_policy(), None);
+
+        assert_ok!(email_client.send_email(&message()).await);
+        // Connections go back to the pool from a background task.
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let connections_after_first_send = sink.state.lock().unwrap().connections;
+        assert_ok!(email_client.send_email(&message()).await);
+
+        let state = sink.state.lock().unwrap();
+        assert_eq!(2, state.messages.len());
+        assert_eq!(connections_after_first_send, state.connections);
+    }
+}
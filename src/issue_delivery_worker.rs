@@ -0,0 +1,290 @@
+use crate::action_links::{ActionLinkSigner, LinkAction};
+use crate::configuration::Settings;
+use crate::domain::{SubscriberEmail, SubscriptionStatus};
+use crate::email_client::{Email, EmailClientError, EmailSender};
+use crate::startup::get_connection;
+use chrono::Utc;
+use sqlx::{PgPool, Postgres, Transaction};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+// A task that keeps failing is dropped after this many attempts.
+const MAX_DELIVERY_ATTEMPTS: i16 = 5;
+const BASE_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+/// Builds the signed unsubscribe link that goes into every issue, so that
+/// sending one does not need a database row per link.
+pub struct UnsubscribeLinks {
+    pub base_url: String,
+    pub signer: ActionLinkSigner,
+    pub ttl: Duration,
+}
+
+impl UnsubscribeLinks {
+    fn link(&self, subscriber_id: Uuid) -> String {
+        let expires_at = Utc::now() + chrono::Duration::seconds(self.ttl.as_secs() as i64);
+        format!(
+            "{}/subscriptions/unsubscribe?token={}",
+            self.base_url,
+            self.signer
+                .sign(subscriber_id, LinkAction::Unsubscribe, expires_at)
+        )
+    }
+}
+
+/// `email_client` should be the one the API sends with: they then share a
+/// single send rate limit.
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    email_client: Arc<dyn EmailSender>,
+) -> Result<(), anyhow::Error> {
+    let connection = get_connection(&configuration.database_settings);
+
+    let unsubscribe_links = UnsubscribeLinks {
+        base_url: configuration.application_settings.base_url,
+        signer: configuration.action_links.signer(),
+        ttl: configuration.action_links.unsubscribe_link_ttl(),
+    };
+
+    worker_loop(connection, email_client, unsubscribe_links).await
+}
+
+async fn worker_loop(
+    connection: PgPool,
+    email_client: Arc<dyn EmailSender>,
+    unsubscribe_links: UnsubscribeLinks,
+) -> Result<(), anyhow::Error> {
+    loop {
+        match try_execute_task(&connection, email_client.as_ref(), &unsubscribe_links).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id = tracing::field::Empty,
+        subscriber_email = tracing::field::Empty
+    ),
+    err
+)]
+pub async fn try_execute_task(
+    connection: &PgPool,
+    email_client: &dyn EmailSender,
+    unsubscribe_links: &UnsubscribeLinks,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let task = dequeue_task(connection).await?;
+    let (transaction, task) = match task {
+        Some(task) => task,
+        None => return Ok(ExecutionOutcome::EmptyQueue),
+    };
+    Span::current()
+        .record("newsletter_issue_id", display(task.newsletter_issue_id))
+        .record("subscriber_email", display(&task.subscriber_email));
+
+    let email = match SubscriberEmail::parse(task.subscriber_email.clone()) {
+        Ok(email) => email,
+        Err(error) => {
+            tracing::warn!(
+                "Skipping a confirmed subscriber. Their stored contact details are invalid: {}",
+                error
+            );
+            delete_task(transaction, &task).await?;
+            return Ok(ExecutionOutcome::TaskCompleted);
+        }
+    };
+
+    let Some(subscriber_id) =
+        get_confirmed_subscriber_id(connection, &task.subscriber_email).await?
+    else {
+        tracing::info!("Skipping a subscriber who is no longer confirmed.");
+        delete_task(transaction, &task).await?;
+        return Ok(ExecutionOutcome::TaskCompleted);
+    };
+
+    let issue = get_issue(connection, task.newsletter_issue_id).await?;
+    let unsubscribe_link = unsubscribe_links.link(subscriber_id);
+    let html_content = format!(
+        "{}<p><a href=\"{}\">Unsubscribe</a></p>",
+        issue.html_content, unsubscribe_link
+    );
+    let text_content = format!(
+        "{}\n\nUnsubscribe: {}",
+        issue.text_content, unsubscribe_link
+    );
+    // RFC 8058: mail clients offering one-click unsubscribe POST to the
+    // `List-Unsubscribe` URL.
+    let email = Email::new(email, issue.title, html_content, text_content)
+        .tag("newsletter")
+        .header("List-Unsubscribe", format!("<{}>", unsubscribe_link))
+        .header("List-Unsubscribe-Post", "List-Unsubscribe=One-Click");
+    match email_client.send_email(&email).await {
+        Ok(()) => delete_task(transaction, &task).await?,
+        Err(EmailClientError::RateLimited {
+            retry_after: Some(retry_after),
+        }) => {
+            // Throttling is not the subscriber's fault: wait as long as the
+            // provider asks without burning one of the task's attempts.
+            tracing::warn!(
+                "The email provider is rate limiting us, retrying in {:?}",
+                retry_after
+            );
+            postpone_task(transaction, &task, task.n_retries, retry_after).await?;
+        }
+        Err(error) if task.n_retries + 1 >= MAX_DELIVERY_ATTEMPTS => {
+            tracing::error!(
+                "Failed to deliver issue to a confirmed subscriber after {} attempts, giving up: {:?}",
+                MAX_DELIVERY_ATTEMPTS,
+                error
+            );
+            delete_task(transaction, &task).await?;
+        }
+        Err(error) => {
+            tracing::warn!(
+                "Failed to deliver issue to a confirmed subscriber, rescheduling: {:?}",
+                error
+            );
+            let n_retries = task.n_retries + 1;
+            let delay = BASE_RETRY_DELAY * 2u32.pow(task.n_retries as u32);
+            postpone_task(transaction, &task, n_retries, delay).await?;
+        }
+    }
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i16,
+}
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+#[tracing::instrument(skip_all)]
+async fn dequeue_task(
+    connection: &PgPool,
+) -> Result<Option<(PgTransaction, DeliveryTask)>, anyhow::Error> {
+    let mut transaction = connection.begin().await?;
+    let task = sqlx::query_as!(
+        DeliveryTask,
+        r#"
+        SELECT newsletter_issue_id, subscriber_email, n_retries
+        FROM issue_delivery_queue
+        WHERE execute_after <= now()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#,
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+
+    Ok(task.map(|task| (transaction, task)))
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    mut transaction: PgTransaction,
+    task: &DeliveryTask,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn postpone_task(
+    mut transaction: PgTransaction,
+    task: &DeliveryTask,
+    n_retries: i16,
+    delay: Duration,
+) -> Result<(), anyhow::Error> {
+    let execute_after = Utc::now() + chrono::Duration::from_std(delay)?;
+
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET n_retries = $3, execute_after = $4
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        n_retries,
+        execute_after
+    )
+    .execute(&mut transaction)
+    .await?;
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+/// Subscribers who left the list after the issue was queued are not found,
+/// and must not get the issue.
+#[tracing::instrument(skip_all)]
+async fn get_confirmed_subscriber_id(
+    connection: &PgPool,
+    subscriber_email: &str,
+) -> Result<Option<Uuid>, anyhow::Error> {
+    let record = sqlx::query!(
+        r#"SELECT id FROM subscriptions WHERE email = $1 AND status = $2"#,
+        subscriber_email,
+        SubscriptionStatus::Confirmed as SubscriptionStatus
+    )
+    .fetch_optional(connection)
+    .await?;
+
+    Ok(record.map(|record| record.id))
+}
+
+struct NewsletterIssue {
+    title: String,
+    text_content: String,
+    html_content: String,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue(connection: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT title, text_content, html_content
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(connection)
+    .await?;
+
+    Ok(issue)
+}
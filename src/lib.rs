@@ -1,6 +1,16 @@
+pub mod action_links;
+pub mod authentication;
+pub mod client_metadata;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod idempotency;
+pub mod issue_delivery_worker;
+pub mod metrics;
+pub mod problem_details;
 pub mod routes;
+pub mod session_state;
+pub mod session_store;
 pub mod startup;
 pub mod telemetry;
+pub mod utils;
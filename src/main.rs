@@ -1,16 +1,76 @@
+use anyhow::Context;
+use secrecy::Secret;
+use std::fmt::{Debug, Display};
+use tokio::task::JoinError;
+use zero2prod::authentication::upsert_user;
 use zero2prod::configuration::Settings;
-use zero2prod::startup::Application;
+use zero2prod::issue_delivery_worker::run_worker_until_stopped;
+use zero2prod::startup::{get_connection, Application};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 
 #[tokio::main]
-async fn main() -> std::io::Result<()> {
+async fn main() -> anyhow::Result<()> {
     let configuration = Settings::new().expect("Failed to read configuration");
 
     let subscriber = get_subscriber("zero2prod".into(), "info".into(), std::io::stdout);
     init_subscriber(subscriber);
 
-    let application = Application::build(configuration).await?;
-    application.run_until_stopped().await?;
+    match std::env::args().nth(1).as_deref() {
+        None => {}
+        Some("create-admin") => return create_admin(configuration).await,
+        Some(command) => anyhow::bail!("Unknown command `{}`.", command),
+    }
+
+    let email_client = configuration.email_client.clone().client();
+    let application = Application::build(configuration.clone(), email_client.clone()).await?;
+    let application_task = tokio::spawn(application.run_until_stopped());
+    let worker_task = tokio::spawn(run_worker_until_stopped(configuration, email_client));
+
+    tokio::select! {
+        outcome = application_task => report_exit("API", outcome),
+        outcome = worker_task => report_exit("Background worker", outcome),
+    };
 
     Ok(())
 }
+
+/// Creates the admin account named by `ADMIN_USERNAME` (`admin` by default),
+/// or resets its password, with the password taken from `ADMIN_PASSWORD`.
+/// Meant to be run once against a fresh deployment, with the password coming
+/// from the platform's secret store.
+async fn create_admin(configuration: Settings) -> anyhow::Result<()> {
+    let username = std::env::var("ADMIN_USERNAME").unwrap_or_else(|_| "admin".into());
+    let password = std::env::var("ADMIN_PASSWORD")
+        .map(Secret::new)
+        .context("Set ADMIN_PASSWORD to the password of the admin account.")?;
+
+    let connection = get_connection(&configuration.database_settings);
+    let user_id = upsert_user(&username, password, &connection).await?;
+    tracing::info!("Admin account {} ({}) is ready", username, user_id);
+
+    Ok(())
+}
+
+fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(())) => {
+            tracing::info!("{} has exited", task_name)
+        }
+        Ok(Err(error)) => {
+            tracing::error!(
+                error.cause_chain = ?error,
+                error.message = %error,
+                "{} failed",
+                task_name
+            )
+        }
+        Err(error) => {
+            tracing::error!(
+                error.cause_chain = ?error,
+                error.message = %error,
+                "{} task failed to complete",
+                task_name
+            )
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use actix_web::body::MessageBody;
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web_lab::middleware::Next;
+use once_cell::sync::Lazy;
+use prometheus::{
+    HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec, Opts, Registry,
+};
+use sqlx::PgPool;
+use std::time::Instant;
+
+/// Every metric the application exposes at `/metrics`.
+///
+/// There is one set per process: the API and the delivery worker run side by
+/// side and report into the same registry.
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+pub struct Metrics {
+    registry: Registry,
+    pub http_requests_total: IntCounterVec,
+    pub http_request_duration_seconds: HistogramVec,
+    pub db_pool_connections: IntGaugeVec,
+    pub emails_sent_total: IntCounterVec,
+    pub subscriptions_created_total: IntCounter,
+    pub subscriptions_confirmed_total: IntCounter,
+    pub subscriptions_unsubscribed_total: IntCounter,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+        let metrics = Self {
+            http_requests_total: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled."),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_request_duration_seconds: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "Time spent handling HTTP requests.",
+                ),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            db_pool_connections: IntGaugeVec::new(
+                Opts::new(
+                    "db_pool_connections",
+                    "Postgres connections held by the pool, by state.",
+                ),
+                &["state"],
+            )
+            .unwrap(),
+            emails_sent_total: IntCounterVec::new(
+                Opts::new("emails_sent_total", "Emails handed to the email backend."),
+                &["backend", "outcome"],
+            )
+            .unwrap(),
+            subscriptions_created_total: IntCounter::new(
+                "subscriptions_created_total",
+                "New subscribers, pending confirmation.",
+            )
+            .unwrap(),
+            subscriptions_confirmed_total: IntCounter::new(
+                "subscriptions_confirmed_total",
+                "Subscriptions confirmed by their subscriber.",
+            )
+            .unwrap(),
+            subscriptions_unsubscribed_total: IntCounter::new(
+                "subscriptions_unsubscribed_total",
+                "Subscriptions ended by their subscriber.",
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: [Box<dyn prometheus::core::Collector>; 7] = [
+            Box::new(metrics.http_requests_total.clone()),
+            Box::new(metrics.http_request_duration_seconds.clone()),
+            Box::new(metrics.db_pool_connections.clone()),
+            Box::new(metrics.emails_sent_total.clone()),
+            Box::new(metrics.subscriptions_created_total.clone()),
+            Box::new(metrics.subscriptions_confirmed_total.clone()),
+            Box::new(metrics.subscriptions_unsubscribed_total.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("Failed to register a metric.");
+        }
+
+        metrics
+    }
+
+    /// Pool statistics are read when scraped rather than kept up to date.
+    /// sqlx does not report how long requests wait for a connection: a pool
+    /// with no idle connection left is where waits start.
+    pub fn observe_pool(&self, pool: &PgPool) {
+        let size = i64::from(pool.size());
+        let idle = pool.num_idle() as i64;
+        self.db_pool_connections
+            .with_label_values(&["idle"])
+            .set(idle);
+        self.db_pool_connections
+            .with_label_values(&["in_use"])
+            .set(size - idle);
+    }
+
+    /// The registry in the Prometheus text exposition format.
+    pub fn render(&self) -> Result<String, prometheus::Error> {
+        prometheus::TextEncoder::new().encode_to_string(&self.registry.gather())
+    }
+}
+
+/// Counts and times every request by method, status and route pattern, e.g.
+/// `/subscriptions/{subscription_id}`. Paths that match no route share the
+/// `unmatched` label, so that scanners cannot blow up the label space.
+pub async fn record_http_metrics(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let start = Instant::now();
+    let method = req.method().to_string();
+
+    let outcome = next.call(req).await;
+
+    let (route, status) = match &outcome {
+        Ok(response) => (
+            response.request().match_pattern(),
+            response.status().as_u16(),
+        ),
+        Err(error) => (None, error.as_response_error().status_code().as_u16()),
+    };
+    let route = route.unwrap_or_else(|| "unmatched".into());
+    let labels = [method.as_str(), route.as_str(), &status.to_string()];
+    METRICS.http_requests_total.with_label_values(&labels).inc();
+    METRICS
+        .http_request_duration_seconds
+        .with_label_values(&labels)
+        .observe(start.elapsed().as_secs_f64());
+
+    outcome
+}
@@ -0,0 +1,119 @@
+use actix_web::body::{BoxBody, EitherBody};
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header::{HeaderValue, CONTENT_TYPE};
+use actix_web::http::StatusCode;
+use actix_web::middleware::ErrorHandlerResponse;
+use actix_web::HttpResponse;
+
+pub const PROBLEM_JSON: &str = "application/problem+json";
+
+/// An RFC 7807 error body. Every problem is of the generic `about:blank`
+/// type, so the title is the status' reason phrase and `detail` says what
+/// went wrong this time.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    #[schema(example = "about:blank")]
+    pub problem_type: String,
+    pub title: String,
+    pub status: u16,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// What is wrong with one field of a request, with a machine-readable `code`
+/// such as `name.too_long`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct FieldError {
+    #[schema(example = "name")]
+    pub field: String,
+    #[schema(example = "name.too_long")]
+    pub code: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, code: &str, message: impl ToString) -> Self {
+        Self {
+            field: field.into(),
+            code: code.into(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl ProblemDetails {
+    pub fn new(status: StatusCode) -> Self {
+        Self {
+            problem_type: "about:blank".into(),
+            title: status.canonical_reason().unwrap_or_default().into(),
+            status: status.as_u16(),
+            detail: None,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn detail(mut self, detail: impl ToString) -> Self {
+        self.detail = Some(detail.to_string());
+        self
+    }
+
+    pub fn errors(mut self, errors: Vec<FieldError>) -> Self {
+        self.errors = errors;
+        self
+    }
+
+    fn body(&self) -> String {
+        serde_json::to_string(self).expect("Failed to serialize problem details.")
+    }
+}
+
+impl From<ProblemDetails> for HttpResponse {
+    fn from(problem: ProblemDetails) -> Self {
+        HttpResponse::build(
+            StatusCode::from_u16(problem.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR),
+        )
+        .content_type(PROBLEM_JSON)
+        .body(problem.body())
+    }
+}
+
+/// Gives every error response without a body of its own - or with the plain
+/// text actix-web falls back to - a problem details body. Pages rendered on
+/// purpose, like the HTML shown for a stale confirmation link, are left alone.
+///
+/// Client errors carry the error message as `detail`; server errors do not,
+/// since their message is meant for the logs. The error stays attached to the
+/// response either way, so it is still logged.
+pub fn render_problem_details<B>(
+    response: ServiceResponse<B>,
+) -> actix_web::Result<ErrorHandlerResponse<B>> {
+    let has_own_body = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| !content_type.starts_with("text/plain"));
+    if has_own_body {
+        return Ok(ErrorHandlerResponse::Response(
+            response.map_into_left_body(),
+        ));
+    }
+
+    let status = response.status();
+    let mut problem = ProblemDetails::new(status);
+    if status.is_client_error() {
+        if let Some(error) = response.response().error() {
+            problem = problem.detail(error);
+        }
+    }
+
+    let response = response.map_body(|head, _| {
+        head.headers_mut()
+            .insert(CONTENT_TYPE, HeaderValue::from_static(PROBLEM_JSON));
+        EitherBody::right(BoxBody::new(problem.body()))
+    });
+
+    Ok(ErrorHandlerResponse::Response(response))
+}
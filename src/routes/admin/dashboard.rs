@@ -0,0 +1,59 @@
+use crate::authentication::UserId;
+use crate::utils::{e500, escape_html};
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn admin_dashboard(
+    user_id: web::ReqData<UserId>,
+    connection: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let username = get_username(*user_id.into_inner(), &connection)
+        .await
+        .map_err(e500)?;
+    let username = escape_html(&username);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Admin dashboard</title>
+</head>
+<body>
+    <p>Welcome {username}!</p>
+    <p>Available actions:</p>
+    <ol>
+        <li><a href="/admin/newsletters">Send a newsletter issue</a></li>
+        <li>
+            <form action="/admin/subscribers/timeline" method="get">
+                <label>Subscriber history
+                    <input type="email" placeholder="Subscriber email" name="email">
+                </label>
+                <button type="submit">Show</button>
+            </form>
+        </li>
+        <li>
+            <form name="logoutForm" action="/admin/logout" method="post">
+                <input type="submit" value="Logout">
+            </form>
+        </li>
+    </ol>
+</body>
+</html>"#,
+        )))
+}
+
+#[tracing::instrument(name = "Get username", skip(connection))]
+pub async fn get_username(user_id: Uuid, connection: &PgPool) -> Result<String, anyhow::Error> {
+    let row = sqlx::query!(r#"SELECT username FROM users WHERE user_id = $1"#, user_id,)
+        .fetch_one(connection)
+        .await
+        .context("Failed to perform a query to retrieve a username.")?;
+
+    Ok(row.username)
+}
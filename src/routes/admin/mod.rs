@@ -0,0 +1,7 @@
+mod dashboard;
+mod logout;
+mod subscribers;
+
+pub use dashboard::admin_dashboard;
+pub use logout::log_out;
+pub use subscribers::*;
@@ -0,0 +1,249 @@
+use crate::domain::{SubscriptionActor, SubscriptionStatus};
+use crate::problem_details::ProblemDetails;
+use crate::utils::{e500, escape_html};
+use actix_web::http::header::{ContentDisposition, ContentType, DispositionParam, DispositionType};
+use actix_web::{web, HttpResponse};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, utoipa::IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct TimelineParameters {
+    /// The subscriber's email address.
+    email: String,
+}
+
+struct Subscriber {
+    id: Uuid,
+    name: String,
+    email: String,
+    status: SubscriptionStatus,
+}
+
+struct SubscriptionEvent {
+    occurred_at: DateTime<Utc>,
+    old_status: Option<SubscriptionStatus>,
+    new_status: SubscriptionStatus,
+    actor: SubscriptionActor,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+/// Every status change of one subscription, oldest first.
+pub async fn subscriber_timeline(
+    parameters: web::Query<TimelineParameters>,
+    connection: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(subscriber) = get_subscriber(&connection, &parameters.email)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound()
+            .content_type(ContentType::html())
+            .body(format!(
+                r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscriber timeline</title>
+</head>
+<body>
+    <p>There is no subscriber with the email {}.</p>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+                escape_html(&parameters.email)
+            )));
+    };
+    let events = get_subscription_events(&connection, subscriber.id)
+        .await
+        .map_err(e500)?;
+
+    let mut rows_html = String::new();
+    for event in &events {
+        writeln!(
+            rows_html,
+            "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+            event.occurred_at.format("%Y-%m-%d %H:%M:%S"),
+            event.old_status.map_or("", |status| status.as_str()),
+            event.new_status.as_str(),
+            event.actor.as_str(),
+            escape_html(event.ip_address.as_deref().unwrap_or("")),
+            escape_html(event.user_agent.as_deref().unwrap_or("")),
+        )
+        .unwrap();
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Subscriber timeline</title>
+</head>
+<body>
+    <h1>{name} &lt;{email}&gt;</h1>
+    <p>Current status: {status}</p>
+    <table>
+        <tr><th>When</th><th>From</th><th>To</th><th>Actor</th><th>IP address</th><th>User agent</th></tr>
+        {rows_html}
+    </table>
+    <form action="/admin/subscribers/consent" method="get">
+        <input hidden type="email" name="email" value="{email}">
+        <button type="submit">Download consent records</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+            name = escape_html(&subscriber.name),
+            email = escape_html(&subscriber.email),
+            status = subscriber.status.as_str(),
+        )))
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct ConsentExport {
+    subscriber_id: Uuid,
+    name: String,
+    email: String,
+    status: SubscriptionStatus,
+    consents: Vec<ConsentRecord>,
+}
+
+#[derive(serde::Serialize, utoipa::ToSchema)]
+struct ConsentRecord {
+    privacy_policy_version: String,
+    form_source: Option<String>,
+    consented_at: DateTime<Utc>,
+    consent_ip_address: Option<String>,
+    consent_user_agent: Option<String>,
+    confirmed_at: Option<DateTime<Utc>>,
+    confirmation_ip_address: Option<String>,
+    confirmation_user_agent: Option<String>,
+}
+
+/// The consent evidence kept for one subscriber, oldest first, as a JSON
+/// download suitable for answering an access request.
+#[utoipa::path(
+    get,
+    path = "/api/v1/admin/subscribers/consent",
+    tag = "admin",
+    params(TimelineParameters),
+    responses(
+        (status = 200, description = "The consent records of the subscriber, as an attachment.",
+            body = ConsentExport),
+        (status = 401, description = "The credentials are missing or wrong.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 404, description = "There is no subscriber with this email.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("basic_auth" = []))
+)]
+pub async fn subscriber_consent_export(
+    parameters: web::Query<TimelineParameters>,
+    connection: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(subscriber) = get_subscriber(&connection, &parameters.email)
+        .await
+        .map_err(e500)?
+    else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+    let consents = get_consent_records(&connection, subscriber.id)
+        .await
+        .map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!(
+                "consent-{}.json",
+                subscriber.id
+            ))],
+        })
+        .json(ConsentExport {
+            subscriber_id: subscriber.id,
+            name: subscriber.name,
+            email: subscriber.email,
+            status: subscriber.status,
+            consents,
+        }))
+}
+
+#[tracing::instrument(name = "Get subscriber by email", skip(connection))]
+async fn get_subscriber(
+    connection: &PgPool,
+    email: &str,
+) -> Result<Option<Subscriber>, anyhow::Error> {
+    sqlx::query_as!(
+        Subscriber,
+        r#"
+        SELECT id, name, email, status AS "status: SubscriptionStatus"
+        FROM subscriptions
+        WHERE email = $1
+        "#,
+        email
+    )
+    .fetch_optional(connection)
+    .await
+    .context("Failed to retrieve the subscriber.")
+}
+
+#[tracing::instrument(name = "Get subscription events", skip(connection))]
+async fn get_subscription_events(
+    connection: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<SubscriptionEvent>, anyhow::Error> {
+    sqlx::query_as!(
+        SubscriptionEvent,
+        r#"
+        SELECT
+            occurred_at,
+            old_status AS "old_status: SubscriptionStatus",
+            new_status AS "new_status: SubscriptionStatus",
+            actor AS "actor: SubscriptionActor",
+            ip_address,
+            user_agent
+        FROM subscription_events
+        WHERE subscriber_id = $1
+        ORDER BY occurred_at, id
+        "#,
+        subscriber_id
+    )
+    .fetch_all(connection)
+    .await
+    .context("Failed to retrieve the subscription events.")
+}
+
+#[tracing::instrument(name = "Get consent records", skip(connection))]
+async fn get_consent_records(
+    connection: &PgPool,
+    subscriber_id: Uuid,
+) -> Result<Vec<ConsentRecord>, anyhow::Error> {
+    sqlx::query_as!(
+        ConsentRecord,
+        r#"
+        SELECT
+            privacy_policy_version,
+            form_source,
+            consented_at,
+            consent_ip_address,
+            consent_user_agent,
+            confirmed_at,
+            confirmation_ip_address,
+            confirmation_user_agent
+        FROM consent_records
+        WHERE subscriber_id = $1
+        ORDER BY consented_at, id
+        "#,
+        subscriber_id
+    )
+    .fetch_all(connection)
+    .await
+    .context("Failed to retrieve the consent records.")
+}
@@ -0,0 +1,61 @@
+use actix_web::http::header::ContentType;
+use actix_web::HttpResponse;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// The contract of the `/api/v1` endpoints.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "Newsletter API", version = "1"),
+    paths(
+        crate::routes::subscribe,
+        crate::routes::get_subscription_by_id,
+        crate::routes::publish_newsletter,
+        crate::routes::subscriber_consent_export,
+    ),
+    tags(
+        (name = "subscriptions", description = "Joining the newsletter."),
+        (name = "admin", description = "Running the newsletter. Requires an admin account."),
+    ),
+    modifiers(&BasicAuthentication)
+)]
+pub struct ApiDoc;
+
+struct BasicAuthentication;
+
+impl Modify for BasicAuthentication {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "basic_auth",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Basic)),
+            );
+        }
+    }
+}
+
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
+
+/// Swagger UI for the document above, loaded from a CDN.
+/// Only mounted when `application_settings.swagger_ui` is enabled.
+pub async fn swagger_ui() -> HttpResponse {
+    HttpResponse::Ok().content_type(ContentType::html()).body(
+        r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Newsletter API</title>
+    <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+    <div id="swagger-ui"></div>
+    <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+    <script>
+        SwaggerUIBundle({ url: "/api/v1/openapi.json", dom_id: "#swagger-ui" });
+    </script>
+</body>
+</html>"##,
+    )
+}
@@ -0,0 +1,141 @@
+use crate::email_client::Mailbox;
+use crate::utils::{e500, escape_html};
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpResponse};
+use std::fmt::Write;
+use uuid::Uuid;
+
+/// Lists the emails captured by the capture email backend.
+/// Only mounted in the local environment.
+pub async fn dev_mailbox(mailbox: web::Data<Mailbox>) -> Result<HttpResponse, actix_web::Error> {
+    let emails = mailbox.list().await.map_err(e500)?;
+
+    let mut rows_html = String::new();
+    for email in &emails {
+        writeln!(
+            rows_html,
+            r#"<tr><td>{}</td><td>{}</td><td><a href="/dev/mailbox/{}">{}</a></td></tr>"#,
+            email.captured_at.format("%Y-%m-%d %H:%M:%S"),
+            escape_html(&email.recipient),
+            email.id,
+            escape_html(&email.subject),
+        )
+        .unwrap();
+    }
+    if emails.is_empty() {
+        rows_html.push_str(r#"<tr><td colspan="3">No emails captured yet.</td></tr>"#);
+    }
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Mailbox</title>
+</head>
+<body>
+    <h1>Captured emails</h1>
+    <table>
+        <tr><th>Captured at</th><th>To</th><th>Subject</th></tr>
+        {rows_html}
+    </table>
+</body>
+</html>"#,
+        )))
+}
+
+/// Renders a single captured email: its headers, the HTML body in a sandboxed
+/// frame and the plain text body with its links made clickable.
+pub async fn dev_mailbox_email(
+    mailbox: web::Data<Mailbox>,
+    id: web::Path<Uuid>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let Some(email) = mailbox.get(id.into_inner()).await.map_err(e500)? else {
+        return Ok(HttpResponse::NotFound().finish());
+    };
+
+    let mut headers_html = String::new();
+    for (name, value) in &email.headers {
+        writeln!(
+            headers_html,
+            "<tr><th>{}</th><td>{}</td></tr>",
+            escape_html(name),
+            escape_html(value)
+        )
+        .unwrap();
+    }
+    if !email.tags.is_empty() {
+        writeln!(
+            headers_html,
+            "<tr><th>Tags</th><td>{}</td></tr>",
+            escape_html(&email.tags.join(", "))
+        )
+        .unwrap();
+    }
+    // Links in the HTML body open in the top window rather than in the frame.
+    let html_document = format!(r#"<base target="_top">{}"#, email.html_content);
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{subject}</title>
+</head>
+<body>
+    <p><a href="/dev/mailbox">&lt;- Back</a></p>
+    <table>
+        {headers_html}
+        <tr><th>Captured at</th><td>{captured_at}</td></tr>
+    </table>
+    <h2>HTML</h2>
+    <iframe sandbox="allow-popups allow-top-navigation" width="100%" height="400" srcdoc="{html_document}"></iframe>
+    <h2>Plain text</h2>
+    <pre>{text_content}</pre>
+</body>
+</html>"#,
+            subject = escape_html(&email.subject),
+            captured_at = email.captured_at.to_rfc3339(),
+            html_document = escape_html(&html_document),
+            text_content = linkify(&email.text_content),
+        )))
+}
+
+/// Escapes plain text and turns every `http(s)://` token into a link.
+fn linkify(text: &str) -> String {
+    let mut html = String::with_capacity(text.len());
+    for (i, line) in text.split('\n').enumerate() {
+        if i > 0 {
+            html.push('\n');
+        }
+        for (j, word) in line.split(' ').enumerate() {
+            if j > 0 {
+                html.push(' ');
+            }
+            let word = escape_html(word);
+            if word.starts_with("http://") || word.starts_with("https://") {
+                write!(html, r#"<a href="{0}">{0}</a>"#, word).unwrap();
+            } else {
+                html.push_str(&word);
+            }
+        }
+    }
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::routes::dev_mailbox::linkify;
+
+    #[test]
+    fn urls_in_plain_text_become_links() {
+        assert_eq!(
+            "Visit <a href=\"https://my-api.com/confirm?a=1&amp;b=2\">https://my-api.com/confirm?a=1&amp;b=2</a>\nto &lt;b&gt;",
+            linkify("Visit https://my-api.com/confirm?a=1&b=2\nto <b>")
+        );
+    }
+}
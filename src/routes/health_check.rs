@@ -1,5 +1,195 @@
-use actix_web::{HttpResponse, Responder};
+use crate::configuration::{DependencyCheck, HealthCheckSettings};
+use crate::email_client::EmailSender;
+use actix_web::{web, HttpResponse, Responder};
+use anyhow::Context;
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::{Connection, PgPool};
+use std::collections::{BTreeMap, HashSet};
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+static MIGRATOR: Migrator = sqlx::migrate!("./migrations");
 
 pub async fn health_check() -> impl Responder {
     HttpResponse::Ok()
 }
+
+#[derive(serde::Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Up,
+    Down,
+}
+
+#[derive(serde::Serialize, Debug)]
+pub struct HealthReport {
+    pub status: HealthStatus,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub checks: BTreeMap<&'static str, CheckReport>,
+}
+
+/// The outcome of probing one dependency. Anyone can read it, so a failure
+/// only comes with a fixed `detail`: the actual error goes to the logs.
+#[derive(serde::Serialize, Debug)]
+pub struct CheckReport {
+    pub status: HealthStatus,
+    pub required: bool,
+    pub latency_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<&'static str>,
+}
+
+/// The process is up and serving requests. Nothing else is checked, so that
+/// an outage of a dependency never gets the process restarted.
+pub async fn liveness() -> HttpResponse {
+    HttpResponse::Ok().json(HealthReport {
+        status: HealthStatus::Up,
+        checks: BTreeMap::new(),
+    })
+}
+
+/// Whether the application can do its job: Postgres is reachable and fully
+/// migrated and, if configured, the email provider answers. Responds with
+/// `503 Service Unavailable` as soon as a required dependency is down.
+pub async fn readiness(
+    pool: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailSender>,
+    settings: web::Data<HealthCheckSettings>,
+) -> HttpResponse {
+    let timeout = settings.timeout();
+    let (database, migrations, email) = tokio::join!(
+        probe(
+            "database",
+            timeout,
+            true,
+            "Postgres is unreachable.",
+            ping_database(&pool)
+        ),
+        probe(
+            "migrations",
+            timeout,
+            true,
+            "The database schema is not up to date.",
+            check_migrations(&pool)
+        ),
+        async {
+            let required = match settings.email_client {
+                DependencyCheck::Skip => return None,
+                DependencyCheck::Optional => false,
+                DependencyCheck::Required => true,
+            };
+            let check = async {
+                email_client
+                    .check_connection()
+                    .await
+                    .context("Failed to reach the email provider.")
+            };
+            Some(
+                probe(
+                    "email_client",
+                    timeout,
+                    required,
+                    "The email provider is unreachable.",
+                    check,
+                )
+                .await,
+            )
+        }
+    );
+
+    let mut checks = BTreeMap::from([("database", database), ("migrations", migrations)]);
+    if let Some(email) = email {
+        checks.insert("email_client", email);
+    }
+    let status = if checks
+        .values()
+        .any(|check| check.required && check.status == HealthStatus::Down)
+    {
+        HealthStatus::Down
+    } else {
+        HealthStatus::Up
+    };
+
+    let report = HealthReport { status, checks };
+    match status {
+        HealthStatus::Up => HttpResponse::Ok().json(report),
+        HealthStatus::Down => HttpResponse::ServiceUnavailable().json(report),
+    }
+}
+
+async fn probe(
+    name: &str,
+    timeout: Duration,
+    required: bool,
+    failure: &'static str,
+    check: impl Future<Output = Result<(), anyhow::Error>>,
+) -> CheckReport {
+    let start = Instant::now();
+    let outcome = match tokio::time::timeout(timeout, check).await {
+        Ok(outcome) => outcome,
+        Err(_) => Err(anyhow::anyhow!("Timed out after {:?}.", timeout)),
+    };
+    let latency_ms = start.elapsed().as_millis() as u64;
+
+    match outcome {
+        Ok(()) => CheckReport {
+            status: HealthStatus::Up,
+            required,
+            latency_ms,
+            detail: None,
+        },
+        Err(error) => {
+            tracing::warn!("Health check of {} failed: {:?}", name, error);
+            CheckReport {
+                status: HealthStatus::Down,
+                required,
+                latency_ms,
+                detail: Some(failure),
+            }
+        }
+    }
+}
+
+async fn ping_database(pool: &PgPool) -> Result<(), anyhow::Error> {
+    pool.acquire()
+        .await
+        .context("Failed to acquire a Postgres connection.")?
+        .ping()
+        .await
+        .context("Postgres did not answer our ping.")
+}
+
+/// The application does not migrate the database itself: a deployment that
+/// skipped `sqlx migrate run` would fail on the first query that needs it.
+async fn check_migrations(pool: &PgPool) -> Result<(), anyhow::Error> {
+    let mut connection = pool
+        .acquire()
+        .await
+        .context("Failed to acquire a Postgres connection.")?;
+
+    if let Some(version) = connection
+        .dirty_version()
+        .await
+        .context("Failed to read the applied migrations.")?
+    {
+        anyhow::bail!("Migration {} failed halfway.", version);
+    }
+
+    let applied: HashSet<i64> = connection
+        .list_applied_migrations()
+        .await
+        .context("Failed to read the applied migrations.")?
+        .into_iter()
+        .map(|migration| migration.version)
+        .collect();
+    let pending: Vec<String> = MIGRATOR
+        .iter()
+        .filter(|migration| !applied.contains(&migration.version))
+        .map(|migration| migration.version.to_string())
+        .collect();
+    if !pending.is_empty() {
+        anyhow::bail!("Pending migrations: {}.", pending.join(", "));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use crate::metrics::METRICS;
+use crate::startup::MetricsBearerToken;
+use crate::utils::e500;
+use actix_web::error::InternalError;
+use actix_web::http::header::{HeaderMap, AUTHORIZATION, WWW_AUTHENTICATE};
+use actix_web::{web, HttpRequest, HttpResponse};
+use anyhow::Context;
+use secrecy::ExposeSecret;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+
+/// Only served to scrapers presenting `metrics_bearer_token`: the counters
+/// tell how the newsletter is doing.
+pub async fn metrics(
+    request: HttpRequest,
+    bearer_token: web::Data<MetricsBearerToken>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    check_bearer_token(request.headers(), &bearer_token).map_err(|error| {
+        let response = HttpResponse::Unauthorized()
+            .insert_header((WWW_AUTHENTICATE, "Bearer"))
+            .finish();
+        InternalError::from_response(error, response)
+    })?;
+
+    METRICS.observe_pool(&pool);
+    let body = METRICS.render().map_err(e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(prometheus::TEXT_FORMAT)
+        .body(body))
+}
+
+fn check_bearer_token(
+    headers: &HeaderMap,
+    expected: &MetricsBearerToken,
+) -> Result<(), anyhow::Error> {
+    let token = headers
+        .get(AUTHORIZATION)
+        .context("The 'Authorization' header was missing.")?
+        .to_str()
+        .context("The 'Authorization' header was not a valid UTF8 string.")?
+        .strip_prefix("Bearer ")
+        .context("The authorization scheme was not 'Bearer'.")?;
+
+    // Comparing digests keeps the time taken independent of how much of the
+    // token was guessed right.
+    let matches =
+        Sha256::digest(token.as_bytes()) == Sha256::digest(expected.0.expose_secret().as_bytes());
+    if !matches {
+        anyhow::bail!("Invalid bearer token.");
+    }
+
+    Ok(())
+}
@@ -1,7 +1,21 @@
+mod admin;
+mod api_docs;
+mod dev_mailbox;
 mod health_check;
+mod login;
+mod metrics;
+mod newsletters;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
 
+pub use admin::*;
+pub use api_docs::*;
+pub use dev_mailbox::*;
 pub use health_check::*;
+pub use login::*;
+pub use metrics::*;
+pub use newsletters::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_unsubscribe::*;
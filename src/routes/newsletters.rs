@@ -0,0 +1,263 @@
+use crate::authentication::UserId;
+use crate::domain::SubscriptionStatus;
+use crate::idempotency::{save_response, try_processing, IdempotencyKey, NextAction};
+use crate::problem_details::ProblemDetails;
+use crate::utils::{e400, e500, see_other};
+use actix_web::http::header::ContentType;
+use actix_web::{web, Either, HttpRequest, HttpResponse};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use sqlx::{PgPool, Postgres, Transaction};
+use std::fmt::Write;
+use uuid::Uuid;
+
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct BodyData {
+    title: String,
+    content: Content,
+}
+
+/// The same issue in both formats; mail clients pick the one they support.
+#[derive(serde::Deserialize, utoipa::ToSchema)]
+pub struct Content {
+    html: String,
+    text: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    title: String,
+    html_content: String,
+    text_content: String,
+    idempotency_key: String,
+}
+
+struct NewsletterIssue {
+    title: String,
+    html_content: String,
+    text_content: String,
+}
+
+pub async fn publish_newsletter_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
+    let mut messages_html = String::new();
+    for message in flash_messages.iter() {
+        writeln!(messages_html, "<p><i>{}</i></p>", message.content()).unwrap();
+    }
+    let idempotency_key = uuid::Uuid::new_v4();
+
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Publish Newsletter Issue</title>
+</head>
+<body>
+    {messages_html}
+    <form action="/admin/newsletters" method="post">
+        <label>Title:<br>
+            <input
+                type="text"
+                placeholder="Enter the issue title"
+                name="title"
+            >
+        </label>
+        <br>
+        <label>Plain text content:<br>
+            <textarea
+                placeholder="Enter the content in plain text"
+                name="text_content"
+                rows="20"
+                cols="50"
+            ></textarea>
+        </label>
+        <br>
+        <label>HTML content:<br>
+            <textarea
+                placeholder="Enter the content in HTML format"
+                name="html_content"
+                rows="20"
+                cols="50"
+            ></textarea>
+        </label>
+        <br>
+        <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
+        <button type="submit">Publish</button>
+    </form>
+    <p><a href="/admin/dashboard">&lt;- Back</a></p>
+</body>
+</html>"#,
+        ))
+}
+
+/// Publishes an issue either from a JSON API call, with the idempotency key in
+/// the `Idempotency-Key` header, or from the admin HTML form, with the key in a
+/// hidden field.
+#[utoipa::path(
+    post,
+    path = "/api/v1/admin/newsletters",
+    tag = "admin",
+    request_body = BodyData,
+    params(
+        ("Idempotency-Key" = String, Header,
+            description = "Retrying with the same key returns the first response instead of publishing twice.")
+    ),
+    responses(
+        (status = 200, description = "The issue was accepted and will be delivered to every confirmed subscriber."),
+        (status = 400, description = "The idempotency key or the issue is missing or invalid.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 401, description = "The credentials are missing or wrong.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+    ),
+    security(("basic_auth" = []))
+)]
+#[tracing::instrument(
+    name = "Publish a newsletter issue",
+    skip(body, request, connection, user_id),
+    fields(user_id = %*user_id, title = tracing::field::Empty)
+)]
+pub async fn publish_newsletter(
+    body: Either<web::Json<BodyData>, web::Form<FormData>>,
+    request: HttpRequest,
+    connection: web::Data<PgPool>,
+    user_id: web::ReqData<UserId>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let user_id = user_id.into_inner();
+    let (issue, idempotency_key, is_form_submission) = match body {
+        Either::Left(json) => {
+            let BodyData { title, content } = json.into_inner();
+            let idempotency_key = request
+                .headers()
+                .get("Idempotency-Key")
+                .and_then(|value| value.to_str().ok())
+                .map(ToOwned::to_owned);
+            let issue = NewsletterIssue {
+                title,
+                html_content: content.html,
+                text_content: content.text,
+            };
+
+            (issue, idempotency_key, false)
+        }
+        Either::Right(form) => {
+            let FormData {
+                title,
+                html_content,
+                text_content,
+                idempotency_key,
+            } = form.into_inner();
+            let issue = NewsletterIssue {
+                title,
+                html_content,
+                text_content,
+            };
+
+            (issue, Some(idempotency_key), true)
+        }
+    };
+    tracing::Span::current().record("title", issue.title.as_str());
+
+    let idempotency_key: IdempotencyKey = idempotency_key
+        .ok_or_else(|| e400("The 'Idempotency-Key' header is missing."))?
+        .try_into()
+        .map_err(e400)?;
+
+    let mut transaction = match try_processing(&connection, &idempotency_key, *user_id)
+        .await
+        .map_err(e500)?
+    {
+        NextAction::StartProcessing(transaction) => transaction,
+        NextAction::ReturnSavedResponse(saved_response) => {
+            if is_form_submission {
+                success_message().send();
+            }
+            return Ok(saved_response);
+        }
+    };
+
+    let issue_id = insert_newsletter_issue(&mut transaction, &issue)
+        .await
+        .map_err(e500)?;
+    enqueue_delivery_tasks(&mut transaction, issue_id)
+        .await
+        .map_err(e500)?;
+
+    let response = if is_form_submission {
+        success_message().send();
+        see_other("/admin/newsletters")
+    } else {
+        HttpResponse::Ok().finish()
+    };
+    let response = save_response(transaction, &idempotency_key, *user_id, response)
+        .await
+        .map_err(e500)?;
+
+    Ok(response)
+}
+
+fn success_message() -> FlashMessage {
+    FlashMessage::info("The newsletter issue has been accepted - emails will go out shortly.")
+}
+
+#[tracing::instrument(skip_all)]
+async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    issue: &NewsletterIssue,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            published_at
+        )
+        VALUES ($1, $2, $3, $4, now())
+        "#,
+        newsletter_issue_id,
+        issue.title,
+        issue.text_content,
+        issue.html_content
+    )
+    .execute(transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute query {:?}!", error);
+        error
+    })?;
+
+    Ok(newsletter_issue_id)
+}
+
+/// Queues one delivery task per confirmed subscriber; the delivery worker
+/// takes care of actually sending the emails.
+#[tracing::instrument(skip_all)]
+async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_delivery_queue (
+            newsletter_issue_id,
+            subscriber_email
+        )
+        SELECT $1, email
+        FROM subscriptions
+        WHERE status = $2
+        "#,
+        newsletter_issue_id,
+        SubscriptionStatus::Confirmed as SubscriptionStatus
+    )
+    .execute(transaction)
+    .await
+    .map_err(|error| {
+        tracing::error!("Failed to execute query {:?}!", error);
+        error
+    })?;
+
+    Ok(())
+}
@@ -1,108 +1,580 @@
-use crate::domain::{NewSubscriber, SubscriberEmail, SubscriberName};
-use crate::email_client::EmailClient;
-use crate::startup::ApplicationBaseUrl;
-use actix_web::{web, HttpResponse};
-use chrono::Utc;
+use crate::client_metadata::ClientMetadata;
+use crate::domain::{
+    InvalidStatusTransition, NewSubscriber, SubscriberEmail, SubscriberEmailError, SubscriberName,
+    SubscriberNameError, SubscriptionActor, SubscriptionStatus,
+};
+use crate::email_client::{Email, EmailClientError, EmailSender};
+use crate::metrics::METRICS;
+use crate::problem_details::{FieldError, ProblemDetails};
+use crate::startup::{
+    ApplicationBaseUrl, PrivacyPolicyVersion, SubscriptionTokenLength, SubscriptionTokenTtl,
+};
+use crate::utils::{e500, error_chain_fmt};
+use actix_web::http::header::LOCATION;
+use actix_web::http::StatusCode;
+use actix_web::{web, Either, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
 use rand::distributions::Alphanumeric;
-use rand::{thread_rng, Rng};
-use sqlx::{PgPool, Postgres, Transaction};
+use rand::rngs::OsRng;
+use rand::Rng;
+use sha2::{Digest, Sha256};
+use sqlx::{PgConnection, PgPool, Postgres, Transaction};
+use std::time::Duration;
 use uuid::Uuid;
 
-#[derive(serde::Deserialize)]
+#[derive(serde::Deserialize, utoipa::ToSchema)]
 pub struct SubscribeData {
+    #[schema(example = "Ursula Le Guin")]
     name: String,
+    #[schema(example = "ursula_le_guin@gmail.com")]
     email: String,
+    /// Which signup form was used, recorded with the consent. Forms embedded
+    /// in several places should set it in a hidden field.
+    source: Option<String>,
 }
 
 impl TryFrom<SubscribeData> for NewSubscriber {
-    type Error = String;
+    type Error = SubscribeError;
 
+    /// Checks every field, so that all the mistakes are reported at once.
     fn try_from(value: SubscribeData) -> Result<Self, Self::Error> {
-        let name = SubscriberName::parse(value.name)?;
-        let email = SubscriberEmail::parse(value.email)?;
+        match (
+            SubscriberName::parse(value.name),
+            SubscriberEmail::parse(value.email),
+        ) {
+            (Ok(name), Ok(email)) => Ok(NewSubscriber { name, email }),
+            (name, email) => {
+                let name_error = name.err().map(name_field_error);
+                let email_error = email.err().map(email_field_error);
+                Err(SubscribeError::ValidationError(
+                    name_error.into_iter().chain(email_error).collect(),
+                ))
+            }
+        }
+    }
+}
+
+fn name_field_error(error: SubscriberNameError) -> FieldError {
+    FieldError::new("name", error.code(), error)
+}
+
+fn email_field_error(error: SubscriberEmailError) -> FieldError {
+    FieldError::new("email", error.code(), error)
+}
 
-        Ok(NewSubscriber { name, email })
+#[derive(thiserror::Error)]
+pub enum SubscribeError {
+    #[error("The subscriber details are invalid.")]
+    ValidationError(Vec<FieldError>),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for SubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
     }
 }
 
+impl ResponseError for SubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let problem = ProblemDetails::new(self.status_code());
+        match self {
+            Self::ValidationError(errors) => problem.detail(self).errors(errors.clone()).into(),
+            Self::UnexpectedError(_) => problem.into(),
+        }
+    }
+}
+
+/// What the JSON API knows about a subscription. It deliberately leaves out
+/// the subscriber's contact details.
+#[derive(serde::Serialize, serde::Deserialize, Debug, utoipa::ToSchema)]
+pub struct Subscription {
+    pub id: Uuid,
+    pub status: SubscriptionStatus,
+    pub subscribed_at: DateTime<Utc>,
+}
+
+/// Subscribes from the HTML form or from a JSON API call.
+///
+/// Form submissions always get a `200 OK`. JSON calls get a `201 Created`
+/// with the new [`Subscription`] and its `Location`, or a `202 Accepted`
+/// without a body when the address is already on the list - in which case a
+/// new confirmation email is on its way, unless the subscription is already
+/// confirmed.
+#[utoipa::path(
+    post,
+    path = "/api/v1/subscriptions",
+    tag = "subscriptions",
+    request_body(content(
+        (SubscribeData = "application/json"),
+        (SubscribeData = "application/x-www-form-urlencoded")
+    )),
+    responses(
+        (status = 201, description = "The subscription was created and a confirmation email sent.",
+            body = Subscription,
+            headers(("Location" = String, description = "Where the new subscription can be retrieved."))),
+        (status = 202, description = "The address is already on the list. A new confirmation email was sent, unless the subscription is already confirmed."),
+        (status = 200, description = "The subscription was accepted. Form submissions always get this answer."),
+        (status = 400, description = "The subscriber details are invalid.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+        (status = 500, description = "Something went wrong on our side.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[allow(clippy::too_many_arguments)]
 #[tracing::instrument(
     name = "Adding new subscriber.",
-    skip(subscribe_data, connection, email_client, base_url)
+    skip(
+        subscribe_data,
+        client,
+        connection,
+        email_client,
+        base_url,
+        token_ttl,
+        token_length,
+        privacy_policy_version
+    )
 )]
 pub async fn subscribe(
-    subscribe_data: web::Form<SubscribeData>,
+    subscribe_data: Either<web::Json<SubscribeData>, web::Form<SubscribeData>>,
+    client: ClientMetadata,
     connection: web::Data<PgPool>,
-    email_client: web::Data<EmailClient>,
+    email_client: web::Data<dyn EmailSender>,
     base_url: web::Data<ApplicationBaseUrl>,
-) -> HttpResponse {
-    let new_subscriber: NewSubscriber = match subscribe_data.0.try_into() {
-        Ok(new_subscriber) => new_subscriber,
-        Err(_) => return HttpResponse::BadRequest().finish(),
+    token_ttl: web::Data<SubscriptionTokenTtl>,
+    token_length: web::Data<SubscriptionTokenLength>,
+    privacy_policy_version: web::Data<PrivacyPolicyVersion>,
+) -> Result<HttpResponse, SubscribeError> {
+    let (subscribe_data, is_form_submission) = match subscribe_data {
+        Either::Left(json) => (json.into_inner(), false),
+        Either::Right(form) => (form.into_inner(), true),
     };
+    let form_source = subscribe_data
+        .source
+        .clone()
+        .or_else(|| client.referer.clone());
+    let new_subscriber: NewSubscriber = subscribe_data.try_into()?;
 
-    let mut transaction = match connection.begin().await {
-        Ok(transaction) => transaction,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
+    let mut transaction = connection
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
 
-    let subscriber_id = match insert_subscriber(&mut transaction, &new_subscriber).await {
-        Ok(subscriber_id) => subscriber_id,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
+    let existing_subscriber = get_existing_subscriber(&mut transaction, &new_subscriber.email)
+        .await
+        .context("Failed to look up an existing subscriber.")?;
+    let subscription_token = generate_subscription_token(token_length.0);
+
+    let is_new_subscriber = existing_subscriber.is_none();
+
+    let subscriber_id = match existing_subscriber {
+        None => {
+            let subscriber_id =
+                match insert_subscriber(&mut transaction, &new_subscriber, &client).await {
+                    Ok(subscriber_id) => subscriber_id,
+                    // The same address was subscribed since we looked it up.
+                    Err(error) if is_unique_violation(&error) => {
+                        return Ok(already_subscribed_response(is_form_submission))
+                    }
+                    Err(error) => {
+                        return Err(anyhow::Error::new(error)
+                            .context("Failed to insert the new subscriber.")
+                            .into())
+                    }
+                };
+            store_token(
+                &mut transaction,
+                &subscription_token,
+                &subscriber_id,
+                token_ttl.0,
+            )
+            .await
+            .context("Failed to store the confirmation token for the new subscriber.")?;
+
+            subscriber_id
+        }
+        // Confirmed subscribers get the usual success and no email.
+        Some(existing_subscriber)
+            if existing_subscriber.status == SubscriptionStatus::Confirmed =>
+        {
+            return Ok(already_subscribed_response(is_form_submission));
+        }
+        // The first confirmation email got lost, or the subscriber left and
+        // wants back in: either way they have to (re)confirm with a fresh link.
+        Some(existing_subscriber) => {
+            change_subscription_status(
+                &mut transaction,
+                &existing_subscriber.id,
+                SubscriptionStatus::PendingConfirmation,
+                SubscriptionActor::Subscriber,
+                &client,
+            )
+            .await
+            .context("Failed to put the existing subscriber back to pending.")?;
+            rotate_token(
+                &mut transaction,
+                &subscription_token,
+                &existing_subscriber.id,
+                token_ttl.0,
+            )
+            .await
+            .context("Failed to replace the confirmation token of the existing subscriber.")?;
+
+            existing_subscriber.id
+        }
     };
-    let subscription_token = generate_subscription_token();
 
-    if store_token(&mut transaction, &subscription_token, &subscriber_id)
+    let subscription = get_subscription(&mut transaction, &subscriber_id)
         .await
-        .is_err()
-    {
-        return HttpResponse::InternalServerError().finish();
-    }
+        .context("Failed to retrieve the subscription.")?
+        .context("The subscription disappeared before it was committed.")?;
 
-    if transaction.commit().await.is_err() {
-        tracing::error!("failed to commit");
-        return HttpResponse::InternalServerError().finish();
+    let consent = Consent {
+        privacy_policy_version: &privacy_policy_version.0,
+        form_source: form_source.as_deref(),
+        client: &client,
+    };
+    record_consent(&mut transaction, &subscriber_id, &consent)
+        .await
+        .context("Failed to record the consent of the subscriber.")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction storing a new subscriber.")?;
+    if is_new_subscriber {
+        METRICS.subscriptions_created_total.inc();
     }
 
-    if send_confirmation_email(
-        &email_client,
+    send_confirmation_email(
+        email_client.as_ref(),
         new_subscriber,
         &base_url.0,
         &subscription_token,
     )
     .await
-    .is_err()
+    .context("Failed to send a confirmation email.")?;
+
+    if is_form_submission {
+        Ok(HttpResponse::Ok().finish())
+    } else if is_new_subscriber {
+        Ok(HttpResponse::Created()
+            .insert_header((
+                LOCATION,
+                format!("/api/v1/subscriptions/{}", subscription.id),
+            ))
+            .json(subscription))
+    } else {
+        Ok(already_subscribed_response(is_form_submission))
+    }
+}
+
+/// Answering differently would tell anyone whether an address is on the list,
+/// so the form says nothing. The JSON API is documented to tell new and
+/// existing subscriptions apart, but shares nothing about the existing one.
+fn already_subscribed_response(is_form_submission: bool) -> HttpResponse {
+    if is_form_submission {
+        HttpResponse::Ok().finish()
+    } else {
+        HttpResponse::Accepted().finish()
+    }
+}
+
+fn is_unique_violation(error: &sqlx::Error) -> bool {
+    matches!(error, sqlx::Error::Database(error) if error.code().as_deref() == Some("23505"))
+}
+
+/// A subscription as seen by the JSON API; see [`Subscription`].
+#[utoipa::path(
+    get,
+    path = "/api/v1/subscriptions/{subscription_id}",
+    tag = "subscriptions",
+    params(("subscription_id" = Uuid, Path, description = "The id of the subscription.")),
+    responses(
+        (status = 200, description = "The subscription.", body = Subscription),
+        (status = 404, description = "There is no such subscription.",
+            body = ProblemDetails, content_type = "application/problem+json"),
+    )
+)]
+#[tracing::instrument(name = "Get a subscription", skip(connection))]
+pub async fn get_subscription_by_id(
+    subscription_id: web::Path<Uuid>,
+    connection: web::Data<PgPool>,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut connection = connection.acquire().await.map_err(e500)?;
+    match get_subscription(&mut connection, &subscription_id)
+        .await
+        .map_err(e500)?
     {
-        return HttpResponse::InternalServerError().finish();
+        Some(subscription) => Ok(HttpResponse::Ok().json(subscription)),
+        None => Ok(HttpResponse::NotFound().finish()),
     }
+}
+
+#[tracing::instrument(name = "Get subscription", skip(connection))]
+async fn get_subscription(
+    connection: &mut PgConnection,
+    subscription_id: &Uuid,
+) -> Result<Option<Subscription>, sqlx::Error> {
+    sqlx::query_as!(
+        Subscription,
+        r#"
+        SELECT id, status AS "status: SubscriptionStatus", subscribed_at
+        FROM subscriptions
+        WHERE id = $1
+        "#,
+        subscription_id
+    )
+    .fetch_optional(connection)
+    .await
+}
+
+struct Consent<'a> {
+    privacy_policy_version: &'a str,
+    form_source: Option<&'a str>,
+    client: &'a ClientMetadata,
+}
+
+/// Keeps evidence of what the subscriber agreed to, and where. The record is
+/// completed by [`confirm_consent`] once the subscriber confirms.
+#[tracing::instrument(name = "Record consent", skip(transaction, subscriber_id, consent))]
+async fn record_consent(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+    consent: &Consent<'_>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO consent_records (
+            subscriber_id,
+            privacy_policy_version,
+            form_source,
+            consented_at,
+            consent_ip_address,
+            consent_user_agent
+        )
+        VALUES ($1, $2, $3, now(), $4, $5)
+        "#,
+        subscriber_id,
+        consent.privacy_policy_version,
+        consent.form_source,
+        consent.client.ip_address,
+        consent.client.user_agent
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Completes the subscriber's most recent consent with the confirmation.
+#[tracing::instrument(name = "Confirm consent", skip(transaction, subscriber_id, client))]
+pub(crate) async fn confirm_consent(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+    client: &ClientMetadata,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE consent_records
+        SET
+            confirmed_at = now(),
+            confirmation_ip_address = $2,
+            confirmation_user_agent = $3
+        WHERE id = (
+            SELECT id
+            FROM consent_records
+            WHERE subscriber_id = $1 AND confirmed_at IS NULL
+            ORDER BY consented_at DESC, id DESC
+            LIMIT 1
+        )
+        "#,
+        subscriber_id,
+        client.ip_address,
+        client.user_agent
+    )
+    .execute(transaction)
+    .await?;
 
-    HttpResponse::Ok().finish()
+    Ok(())
+}
+
+struct ExistingSubscriber {
+    id: Uuid,
+    status: SubscriptionStatus,
+}
+
+#[tracing::instrument(name = "Looking up an existing subscriber", skip(transaction, email))]
+async fn get_existing_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &SubscriberEmail,
+) -> Result<Option<ExistingSubscriber>, sqlx::Error> {
+    sqlx::query_as!(
+        ExistingSubscriber,
+        r#"
+        SELECT id, status AS "status: SubscriptionStatus"
+        FROM subscriptions
+        WHERE email = $1
+        FOR UPDATE
+        "#,
+        email.as_ref()
+    )
+    .fetch_optional(transaction)
+    .await
+}
+
+#[derive(thiserror::Error, Debug)]
+pub(crate) enum StatusChangeError {
+    #[error(transparent)]
+    InvalidTransition(#[from] InvalidStatusTransition),
+    #[error("There is no subscriber with this id.")]
+    UnknownSubscriber,
+    #[error(transparent)]
+    Database(#[from] sqlx::Error),
+}
+
+/// The one way a subscription's status changes: the row is locked, the
+/// change has to be an allowed [`SubscriptionStatus`] transition, and it is
+/// recorded in the subscription's history.
+/// Returns the status the subscription had before.
+#[tracing::instrument(
+    name = "Change subscription status",
+    skip(transaction, subscriber_id, client)
+)]
+pub(crate) async fn change_subscription_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+    next: SubscriptionStatus,
+    actor: SubscriptionActor,
+    client: &ClientMetadata,
+) -> Result<SubscriptionStatus, StatusChangeError> {
+    let current = sqlx::query!(
+        r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions WHERE id = $1 FOR UPDATE"#,
+        subscriber_id
+    )
+    .fetch_optional(&mut *transaction)
+    .await?
+    .ok_or(StatusChangeError::UnknownSubscriber)?
+    .status;
+
+    if current.transition_to(next)? != current {
+        sqlx::query!(
+            r#"UPDATE subscriptions SET status = $2 WHERE id = $1"#,
+            subscriber_id,
+            next as SubscriptionStatus
+        )
+        .execute(&mut *transaction)
+        .await?;
+
+        record_subscription_event(
+            transaction,
+            subscriber_id,
+            Some(current),
+            next,
+            actor,
+            client,
+        )
+        .await?;
+    }
+
+    Ok(current)
+}
+
+#[tracing::instrument(
+    name = "Record subscription event",
+    skip(transaction, subscriber_id, client)
+)]
+async fn record_subscription_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+    old_status: Option<SubscriptionStatus>,
+    new_status: SubscriptionStatus,
+    actor: SubscriptionActor,
+    client: &ClientMetadata,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO subscription_events
+            (subscriber_id, old_status, new_status, actor, ip_address, user_agent)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+        subscriber_id,
+        old_status as Option<SubscriptionStatus>,
+        new_status as SubscriptionStatus,
+        actor as SubscriptionActor,
+        client.ip_address,
+        client.user_agent
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// Replaces the subscriber's confirmation tokens with a new one, so that only
+/// the most recent confirmation email works.
+#[tracing::instrument(
+    name = "Rotate subscription token",
+    skip(transaction, subscriber_id, subscription_token)
+)]
+pub(crate) async fn rotate_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+    subscriber_id: &Uuid,
+    ttl: Duration,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"DELETE FROM subscription_tokens WHERE subscriber_id = $1"#,
+        subscriber_id
+    )
+    .execute(&mut *transaction)
+    .await?;
+
+    store_token(transaction, subscription_token, subscriber_id, ttl).await
 }
 
 #[tracing::instrument(
     name = "Saving new subscriber details to the database!",
-    skip(new_subscriber, transaction)
+    skip(new_subscriber, transaction, client)
 )]
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    client: &ClientMetadata,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
     sqlx::query!(
         r#"
         INSERT INTO subscriptions (id, name, email, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        VALUES ($1, $2, $3, $4, $5)
         "#,
         subscriber_id,
         new_subscriber.name.as_ref(),
         new_subscriber.email.as_ref(),
-        Utc::now()
+        Utc::now(),
+        SubscriptionStatus::PendingConfirmation as SubscriptionStatus
     )
-    .execute(transaction)
-    .await
-    .map_err(|e| {
-        tracing::error!("Failed to execute query {:?}!", e);
-        e
-    })?;
+    .execute(&mut *transaction)
+    .await?;
+
+    record_subscription_event(
+        transaction,
+        &subscriber_id,
+        None,
+        SubscriptionStatus::PendingConfirmation,
+        SubscriptionActor::Subscriber,
+        client,
+    )
+    .await?;
 
     Ok(subscriber_id)
 }
@@ -112,11 +584,11 @@ pub async fn insert_subscriber(
     skip(email_client, new_subscriber, base_url)
 )]
 pub async fn send_confirmation_email(
-    email_client: &EmailClient,
+    email_client: &dyn EmailSender,
     new_subscriber: NewSubscriber,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), EmailClientError> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
@@ -126,20 +598,32 @@ pub async fn send_confirmation_email(
                  Click <a href=\"{}\">here</a> to confirm your subscription.",
         confirmation_link
     );
+    let plain_body = format!(
+        "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
+        confirmation_link
+    );
 
-    email_client
-        .send_email(new_subscriber.email, "Welcome!", &html_body)
-        .await
+    let email =
+        Email::new(new_subscriber.email, "Welcome!", html_body, plain_body).tag("confirmation");
+
+    email_client.send_email(&email).await
 }
 
-fn generate_subscription_token() -> String {
-    let mut rng = thread_rng();
-    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+pub(crate) fn generate_subscription_token(length: usize) -> String {
+    OsRng
+        .sample_iter(Alphanumeric)
         .map(char::from)
-        .take(25)
+        .take(length)
         .collect()
 }
 
+/// Confirmation tokens are only ever stored as their hex-encoded SHA-256
+/// digest, so the database alone is not enough to confirm a subscription.
+/// The tokens are random, so there is nothing to gain from a slow hash.
+pub(crate) fn hash_subscription_token(subscription_token: &str) -> String {
+    format!("{:x}", Sha256::digest(subscription_token.as_bytes()))
+}
+
 #[tracing::instrument(
     name = "Store subscription token in the database",
     skip(transaction, subscriber_id, subscription_token)
@@ -148,18 +632,105 @@ async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscription_token: &str,
     subscriber_id: &Uuid,
+    ttl: Duration,
 ) -> Result<(), sqlx::Error> {
+    let expires_at = Utc::now() + chrono::Duration::seconds(ttl.as_secs() as i64);
     sqlx::query!(
-        r#"INSERT INTO subscription_tokens (subscription_token, subscriber_id) VALUES ($1, $2)"#,
-        subscription_token,
-        subscriber_id
+        r#"
+        INSERT INTO subscription_tokens (subscription_token_hash, subscriber_id, expires_at)
+        VALUES ($1, $2, $3)
+        "#,
+        hash_subscription_token(subscription_token),
+        subscriber_id,
+        expires_at
     )
     .execute(transaction)
-    .await
-    .map_err(|error| {
-        tracing::error!("Failed to execute query {:?}!", error);
-        error
-    })?;
+    .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::NewSubscriber;
+    use crate::problem_details::FieldError;
+    use crate::routes::subscriptions::{
+        generate_subscription_token, hash_subscription_token, SubscribeData, SubscribeError,
+    };
+    use actix_web::http::StatusCode;
+    use actix_web::ResponseError;
+
+    fn subscribe_data(name: &str, email: &str) -> SubscribeData {
+        SubscribeData {
+            name: name.into(),
+            email: email.into(),
+            source: None,
+        }
+    }
+
+    #[test]
+    fn every_invalid_field_is_reported() {
+        let Err(SubscribeError::ValidationError(errors)) =
+            NewSubscriber::try_from(subscribe_data(&"a".repeat(257), "not-an-email"))
+        else {
+            panic!("The subscriber details were accepted.");
+        };
+
+        assert_eq!(
+            vec![
+                FieldError::new(
+                    "name",
+                    "name.too_long",
+                    "The subscriber name is longer than 256 characters."
+                ),
+                FieldError::new(
+                    "email",
+                    "email.invalid",
+                    "not-an-email is not a valid email address."
+                ),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn invalid_input_is_a_bad_request_and_anything_else_a_server_error() {
+        assert_eq!(
+            StatusCode::BAD_REQUEST,
+            SubscribeError::ValidationError(Vec::new()).status_code()
+        );
+        assert_eq!(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            SubscribeError::from(anyhow::anyhow!("The database is down.")).status_code()
+        );
+    }
+
+    #[test]
+    fn the_debug_output_includes_the_cause() {
+        let error = SubscribeError::from(
+            anyhow::anyhow!("The database is down.").context("Failed to insert the subscriber."),
+        );
+
+        assert_eq!(
+            "Failed to insert the subscriber.\nCaused by:\n\tThe database is down.\n",
+            format!("{:?}", error)
+        );
+    }
+
+    #[test]
+    fn generated_tokens_are_alphanumeric_with_the_requested_length() {
+        let token = generate_subscription_token(40);
+
+        assert_eq!(40, token.len());
+        assert!(token.chars().all(|c| c.is_ascii_alphanumeric()));
+        assert_ne!(token, generate_subscription_token(40));
+    }
+
+    #[test]
+    fn tokens_are_hashed_to_hex_encoded_sha256() {
+        assert_eq!(
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+            hash_subscription_token("abc")
+        );
+    }
+}
@@ -1,76 +1,336 @@
-use actix_web::{web, HttpResponse};
-use sqlx::PgPool;
+use crate::client_metadata::ClientMetadata;
+use crate::domain::{
+    InvalidStatusTransition, NewSubscriber, SubscriberEmail, SubscriberName, SubscriptionActor,
+    SubscriptionStatus,
+};
+use crate::email_client::EmailSender;
+use crate::metrics::METRICS;
+use crate::routes::{
+    change_subscription_status, confirm_consent, generate_subscription_token,
+    hash_subscription_token, rotate_token, send_confirmation_email, StatusChangeError,
+};
+use crate::startup::{ApplicationBaseUrl, SubscriptionTokenLength, SubscriptionTokenTtl};
+use crate::utils::{error_chain_fmt, escape_html};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 #[derive(serde::Deserialize)]
 pub struct Parameters {
     subscription_token: String,
 }
-#[tracing::instrument(name = "Confirm a pending subscriber", skip(parameters, connection))]
+
+struct StoredToken {
+    subscriber_id: Uuid,
+    expires_at: DateTime<Utc>,
+    consumed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(thiserror::Error)]
+pub enum ConfirmError {
+    #[error("The confirmation token is not valid.")]
+    UnknownToken,
+    #[error("The confirmation link has already been used.")]
+    AlreadyUsed,
+    /// Carries the token, so that it can be traded for a new link.
+    #[error("The confirmation link has expired.")]
+    Expired { subscription_token: String },
+    #[error("The subscription has ended since the link was sent.")]
+    SubscriptionEnded(#[source] InvalidStatusTransition),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ConfirmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for ConfirmError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::UnknownToken => StatusCode::UNAUTHORIZED,
+            Self::AlreadyUsed => StatusCode::CONFLICT,
+            Self::Expired { .. } | Self::SubscriptionEnded(_) => StatusCode::GONE,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        match self {
+            Self::AlreadyUsed => response
+                .content_type(ContentType::html())
+                .body(already_used_page()),
+            Self::Expired { subscription_token } => response
+                .content_type(ContentType::html())
+                .body(expired_page(Some(subscription_token))),
+            // An old link must not sign back up someone who has since left
+            // the list.
+            Self::SubscriptionEnded(_) => response
+                .content_type(ContentType::html())
+                .body(expired_page(None)),
+            _ => response.finish(),
+        }
+    }
+}
+
+impl From<StatusChangeError> for ConfirmError {
+    fn from(error: StatusChangeError) -> Self {
+        match error {
+            StatusChangeError::InvalidTransition(error) => Self::SubscriptionEnded(error),
+            StatusChangeError::UnknownSubscriber => Self::UnknownToken,
+            StatusChangeError::Database(error) => Self::UnexpectedError(
+                anyhow::Error::new(error).context("Failed to confirm the subscriber."),
+            ),
+        }
+    }
+}
+
+/// Confirms a pending subscriber. A token works exactly once and only until
+/// it expires; the token is consumed in the same transaction that confirms
+/// the subscriber, so two concurrent clicks cannot both succeed.
+#[tracing::instrument(
+    name = "Confirm a pending subscriber",
+    skip(parameters, client, connection)
+)]
 pub async fn confirm(
     parameters: web::Query<Parameters>,
+    client: ClientMetadata,
     connection: web::Data<PgPool>,
-) -> HttpResponse {
-    let id = match get_subscriber_id_from_token(&connection, &parameters.subscription_token).await {
-        Ok(id) => id,
-        Err(_) => return HttpResponse::InternalServerError().finish(),
-    };
+) -> Result<HttpResponse, ConfirmError> {
+    let subscription_token = &parameters.subscription_token;
 
-    return match id {
-        None => HttpResponse::Unauthorized().finish(),
-        Some(subscriber_id) => {
-            if confirm_subscriber(&connection, &subscriber_id)
-                .await
-                .is_err()
-            {
-                return HttpResponse::InternalServerError().finish();
-            }
-
-            HttpResponse::Ok().finish()
-        }
-    };
+    let mut transaction = connection
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    let token = get_token(&mut transaction, subscription_token)
+        .await
+        .context("Failed to retrieve the confirmation token.")?
+        .ok_or(ConfirmError::UnknownToken)?;
+    if token.consumed_at.is_some() {
+        return Err(ConfirmError::AlreadyUsed);
+    }
+    if token.expires_at <= Utc::now() {
+        return Err(ConfirmError::Expired {
+            subscription_token: subscription_token.clone(),
+        });
+    }
+
+    consume_token(&mut transaction, subscription_token)
+        .await
+        .context("Failed to mark the confirmation token as consumed.")?;
+
+    let previous_status = change_subscription_status(
+        &mut transaction,
+        &token.subscriber_id,
+        SubscriptionStatus::Confirmed,
+        SubscriptionActor::Subscriber,
+        &client,
+    )
+    .await?;
+    let is_confirmation = previous_status == SubscriptionStatus::PendingConfirmation;
+    if is_confirmation {
+        confirm_consent(&mut transaction, &token.subscriber_id, &client)
+            .await
+            .context("Failed to record the confirmation of the consent.")?;
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction confirming a subscriber.")?;
+    if is_confirmation {
+        METRICS.subscriptions_confirmed_total.inc();
+    }
+
+    Ok(HttpResponse::Ok().finish())
 }
 
+/// Sends a new confirmation link to whoever holds an expired one. Confirmed
+/// or unsubscribed subscribers get the same answer but no email.
 #[tracing::instrument(
-    name = "Get subscriber_id from token",
-    skip(connection, subscription_token)
+    name = "Resend a confirmation email",
+    skip(form, connection, email_client, base_url, token_ttl, token_length)
 )]
-async fn get_subscriber_id_from_token(
-    connection: &PgPool,
-    subscription_token: &str,
-) -> Result<Option<Uuid>, sqlx::Error> {
-    let result = sqlx::query!(
-        "SELECT subscriber_id FROM subscription_tokens WHERE subscription_token = $1",
-        subscription_token
+pub async fn resend_confirmation(
+    form: web::Form<Parameters>,
+    connection: web::Data<PgPool>,
+    email_client: web::Data<dyn EmailSender>,
+    base_url: web::Data<ApplicationBaseUrl>,
+    token_ttl: web::Data<SubscriptionTokenTtl>,
+    token_length: web::Data<SubscriptionTokenLength>,
+) -> Result<HttpResponse, ConfirmError> {
+    let mut transaction = connection
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    let token = get_token(&mut transaction, &form.subscription_token)
+        .await
+        .context("Failed to retrieve the confirmation token.")?
+        .ok_or(ConfirmError::UnknownToken)?;
+
+    let Some(subscriber) = get_pending_subscriber(&mut transaction, &token.subscriber_id)
+        .await
+        .context("Failed to retrieve the pending subscriber.")?
+    else {
+        return Ok(link_sent_response());
+    };
+
+    let subscription_token = generate_subscription_token(token_length.0);
+    rotate_token(
+        &mut transaction,
+        &subscription_token,
+        &token.subscriber_id,
+        token_ttl.0,
+    )
+    .await
+    .context("Failed to replace the confirmation token.")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction replacing a confirmation token.")?;
+
+    send_confirmation_email(
+        email_client.as_ref(),
+        subscriber,
+        &base_url.0,
+        &subscription_token,
     )
-    .fetch_optional(connection)
     .await
-    .map_err(|error| {
-        tracing::error!("Failed to execute query {:?}!", error);
-        error
-    })?;
+    .context("Failed to send a confirmation email.")?;
+
+    Ok(link_sent_response())
+}
+
+fn already_used_page() -> String {
+    r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Link already used</title>
+</head>
+<body>
+    <p>This confirmation link has already been used.</p>
+</body>
+</html>"#
+        .to_string()
+}
+
+/// Stored tokens can be traded for a new link right away; everyone else is
+/// asked to subscribe again, which sends one too.
+fn expired_page(subscription_token: Option<&str>) -> String {
+    let next_step = match subscription_token {
+        Some(subscription_token) => format!(
+            r#"<form action="/subscriptions/confirm/resend" method="post">
+        <input hidden type="text" name="subscription_token" value="{}">
+        <button type="submit">Send me a new link</button>
+    </form>"#,
+            escape_html(subscription_token)
+        ),
+        None => "<p>Please subscribe again to receive a new one.</p>".to_string(),
+    };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Link expired</title>
+</head>
+<body>
+    <p>This confirmation link has expired.</p>
+    {next_step}
+</body>
+</html>"#
+    )
+}
 
-    Ok(result.map(|record| record.subscriber_id))
+/// An old link must not sign back up someone who has since left the list.
+fn link_sent_response() -> HttpResponse {
+    HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Check your inbox</title>
+</head>
+<body>
+    <p>If your subscription is still pending, a new confirmation link is on its way.</p>
+</body>
+</html>"#,
+    )
+}
+
+#[tracing::instrument(name = "Get subscription token", skip(transaction, subscription_token))]
+async fn get_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
+) -> Result<Option<StoredToken>, sqlx::Error> {
+    sqlx::query_as!(
+        StoredToken,
+        r#"
+        SELECT subscriber_id, expires_at, consumed_at
+        FROM subscription_tokens
+        WHERE subscription_token_hash = $1
+        FOR UPDATE
+        "#,
+        hash_subscription_token(subscription_token)
+    )
+    .fetch_optional(transaction)
+    .await
 }
 
 #[tracing::instrument(
-    name = "Mark subscriber as confirmed",
-    skip(connection, subscription_id)
+    name = "Mark subscription token as consumed",
+    skip(transaction, subscription_token)
 )]
-async fn confirm_subscriber(
-    connection: &PgPool,
-    subscription_id: &Uuid,
+async fn consume_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscription_token: &str,
 ) -> Result<(), sqlx::Error> {
     sqlx::query!(
-        r#"UPDATE subscriptions SET status = 'confirmed' WHERE id = $1"#,
-        subscription_id
+        r#"UPDATE subscription_tokens SET consumed_at = now() WHERE subscription_token_hash = $1"#,
+        hash_subscription_token(subscription_token)
     )
-    .execute(connection)
-    .await
-    .map_err(|error| {
-        tracing::error!("Failed to execute query {:?}!", error);
-        error
-    })?;
+    .execute(transaction)
+    .await?;
 
     Ok(())
 }
+
+#[tracing::instrument(name = "Get pending subscriber", skip(transaction, subscriber_id))]
+async fn get_pending_subscriber(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: &Uuid,
+) -> Result<Option<NewSubscriber>, anyhow::Error> {
+    let record = sqlx::query!(
+        r#"
+        SELECT name, email
+        FROM subscriptions
+        WHERE id = $1 AND status = $2
+        FOR UPDATE
+        "#,
+        subscriber_id,
+        SubscriptionStatus::PendingConfirmation as SubscriptionStatus
+    )
+    .fetch_optional(transaction)
+    .await?;
+
+    match record {
+        None => Ok(None),
+        Some(record) => Ok(Some(NewSubscriber {
+            name: SubscriberName::parse(record.name)?,
+            email: SubscriberEmail::parse(record.email)?,
+        })),
+    }
+}
@@ -0,0 +1,147 @@
+use crate::action_links::{ActionLinkError, ActionLinkSigner, LinkAction};
+use crate::client_metadata::ClientMetadata;
+use crate::domain::{SubscriptionActor, SubscriptionStatus};
+use crate::metrics::METRICS;
+use crate::routes::{change_subscription_status, StatusChangeError};
+use crate::utils::{error_chain_fmt, escape_html};
+use actix_web::http::header::ContentType;
+use actix_web::http::StatusCode;
+use actix_web::{web, HttpResponse, ResponseError};
+use anyhow::Context;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Newsletter issues carry a signed `token` naming the subscriber.
+#[derive(serde::Deserialize, serde::Serialize)]
+pub struct UnsubscribeParameters {
+    token: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error("The unsubscribe link is not valid.")]
+    InvalidLink(#[source] ActionLinkError),
+    #[error("The subscriber no longer exists.")]
+    UnknownSubscriber,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl ResponseError for UnsubscribeError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            Self::InvalidLink(_) | Self::UnknownSubscriber => StatusCode::UNAUTHORIZED,
+            Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+/// The page behind the unsubscribe link of every newsletter issue. Following
+/// the link does not unsubscribe on its own - link scanners would trigger it -
+/// the subscriber has to confirm with the form below.
+#[tracing::instrument(name = "Show the unsubscribe page", skip(parameters, signer))]
+pub async fn unsubscribe_form(
+    parameters: web::Query<UnsubscribeParameters>,
+    signer: web::Data<ActionLinkSigner>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    get_subscriber_id(&signer, &parameters)?;
+    let query = serde_urlencoded::to_string(&*parameters)
+        .context("Failed to encode the unsubscribe query string.")?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(format!(
+            r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+</head>
+<body>
+    <p>Do you want to stop receiving our newsletter?</p>
+    <form action="/subscriptions/unsubscribe?{}" method="post">
+        <button type="submit">Unsubscribe</button>
+    </form>
+</body>
+</html>"#,
+            escape_html(&query)
+        )))
+}
+
+/// Handles both the form above and RFC 8058 one-click requests, which mail
+/// clients send as a `POST` to the `List-Unsubscribe` URL with a
+/// `List-Unsubscribe=One-Click` body. The body carries nothing we need.
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(parameters, client, connection, signer)
+)]
+pub async fn unsubscribe(
+    parameters: web::Query<UnsubscribeParameters>,
+    client: ClientMetadata,
+    connection: web::Data<PgPool>,
+    signer: web::Data<ActionLinkSigner>,
+) -> Result<HttpResponse, UnsubscribeError> {
+    let subscriber_id = get_subscriber_id(&signer, &parameters)?;
+
+    let mut transaction = connection
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    let previous_status = match change_subscription_status(
+        &mut transaction,
+        &subscriber_id,
+        SubscriptionStatus::Unsubscribed,
+        SubscriptionActor::Subscriber,
+        &client,
+    )
+    .await
+    {
+        Ok(previous_status) => previous_status,
+        Err(StatusChangeError::UnknownSubscriber) => {
+            return Err(UnsubscribeError::UnknownSubscriber)
+        }
+        Err(error) => {
+            return Err(anyhow::Error::new(error)
+                .context("Failed to unsubscribe the subscriber.")
+                .into())
+        }
+    };
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the transaction unsubscribing a subscriber.")?;
+    if previous_status != SubscriptionStatus::Unsubscribed {
+        METRICS.subscriptions_unsubscribed_total.inc();
+    }
+
+    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribed</title>
+</head>
+<body>
+    <p>You have been unsubscribed. You will not receive any more newsletter issues.</p>
+</body>
+</html>"#,
+    ))
+}
+
+/// Resolves the subscriber a request is about.
+fn get_subscriber_id(
+    signer: &ActionLinkSigner,
+    parameters: &UnsubscribeParameters,
+) -> Result<Uuid, UnsubscribeError> {
+    signer
+        .verify(&parameters.token, LinkAction::Unsubscribe)
+        .map_err(UnsubscribeError::InvalidLink)
+}
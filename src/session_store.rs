@@ -0,0 +1,152 @@
+use actix_session::storage::{LoadError, SaveError, SessionKey, SessionStore, UpdateError};
+use actix_web::cookie::time::Duration;
+use anyhow::Context;
+use chrono::Utc;
+use rand::distributions::Alphanumeric;
+use rand::{thread_rng, Rng};
+use sqlx::PgPool;
+use std::collections::HashMap;
+
+type SessionState = HashMap<String, String>;
+
+/// Session storage backed by the `sessions` table, so that we can keep
+/// browser sessions without running anything besides Postgres.
+#[derive(Clone)]
+pub struct PgSessionStore {
+    connection: PgPool,
+}
+
+impl PgSessionStore {
+    pub fn new(connection: PgPool) -> Self {
+        Self { connection }
+    }
+}
+
+#[async_trait::async_trait(?Send)]
+impl SessionStore for PgSessionStore {
+    async fn load(&self, session_key: &SessionKey) -> Result<Option<SessionState>, LoadError> {
+        let row = sqlx::query!(
+            r#"SELECT session_state FROM sessions WHERE session_key = $1 AND expires_at > now()"#,
+            session_key.as_ref()
+        )
+        .fetch_optional(&self.connection)
+        .await
+        .context("Failed to load the session state.")
+        .map_err(LoadError::Other)?;
+
+        row.map(|row| serde_json::from_value(row.session_state))
+            .transpose()
+            .context("Failed to deserialize the session state.")
+            .map_err(LoadError::Deserialization)
+    }
+
+    async fn save(
+        &self,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, SaveError> {
+        let session_state = serde_json::to_value(session_state)
+            .context("Failed to serialize the session state.")
+            .map_err(SaveError::Serialization)?;
+        let session_key = generate_session_key();
+
+        sqlx::query!(r#"DELETE FROM sessions WHERE expires_at <= now()"#)
+            .execute(&self.connection)
+            .await
+            .context("Failed to purge expired sessions.")
+            .map_err(SaveError::Other)?;
+
+        sqlx::query!(
+            r#"INSERT INTO sessions (session_key, session_state, expires_at) VALUES ($1, $2, $3)"#,
+            session_key,
+            session_state,
+            Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+        )
+        .execute(&self.connection)
+        .await
+        .context("Failed to save the session state.")
+        .map_err(SaveError::Other)?;
+
+        session_key
+            .try_into()
+            .context("Failed to convert the generated session key.")
+            .map_err(SaveError::Other)
+    }
+
+    async fn update(
+        &self,
+        session_key: SessionKey,
+        session_state: SessionState,
+        ttl: &Duration,
+    ) -> Result<SessionKey, UpdateError> {
+        let serialized_state = serde_json::to_value(&session_state)
+            .context("Failed to serialize the session state.")
+            .map_err(UpdateError::Serialization)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE sessions
+            SET session_state = $2, expires_at = $3
+            WHERE session_key = $1 AND expires_at > now()
+            "#,
+            session_key.as_ref(),
+            serialized_state,
+            Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+        )
+        .execute(&self.connection)
+        .await
+        .context("Failed to update the session state.")
+        .map_err(UpdateError::Other)?;
+
+        if result.rows_affected() == 0 {
+            // The session expired in between the load and the update:
+            // persist the state under a brand new key.
+            return self
+                .save(session_state, ttl)
+                .await
+                .map_err(|error| match error {
+                    SaveError::Serialization(error) => UpdateError::Serialization(error),
+                    SaveError::Other(error) => UpdateError::Other(error),
+                });
+        }
+
+        Ok(session_key)
+    }
+
+    async fn update_ttl(
+        &self,
+        session_key: &SessionKey,
+        ttl: &Duration,
+    ) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"UPDATE sessions SET expires_at = $2 WHERE session_key = $1"#,
+            session_key.as_ref(),
+            Utc::now() + chrono::Duration::seconds(ttl.whole_seconds())
+        )
+        .execute(&self.connection)
+        .await
+        .context("Failed to update the session time-to-live.")?;
+
+        Ok(())
+    }
+
+    async fn delete(&self, session_key: &SessionKey) -> Result<(), anyhow::Error> {
+        sqlx::query!(
+            r#"DELETE FROM sessions WHERE session_key = $1"#,
+            session_key.as_ref()
+        )
+        .execute(&self.connection)
+        .await
+        .context("Failed to delete the session.")?;
+
+        Ok(())
+    }
+}
+
+fn generate_session_key() -> String {
+    let mut rng = thread_rng();
+    std::iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(64)
+        .collect()
+}
@@ -1,32 +1,164 @@
-use crate::configuration::{DatabaseSettings, Settings};
-use crate::email_client::EmailClient;
-use crate::routes::{confirm, health_check, subscribe};
+use crate::action_links::ActionLinkSigner;
+use crate::authentication::reject_anonymous_users;
+use crate::configuration::{
+    ApplicationSettings, DatabaseSettings, Environment, HealthCheckSettings, Settings,
+};
+use crate::email_client::{EmailSender, Mailbox};
+use crate::metrics::record_http_metrics;
+use crate::problem_details::render_problem_details;
+use crate::routes::{
+    admin_dashboard, confirm, dev_mailbox, dev_mailbox_email, get_subscription_by_id, health_check,
+    liveness, log_out, login, login_form, metrics, openapi_json, publish_newsletter,
+    publish_newsletter_form, readiness, resend_confirmation, subscribe, subscriber_consent_export,
+    subscriber_timeline, swagger_ui, unsubscribe, unsubscribe_form,
+};
+use crate::session_store::PgSessionStore;
+use actix_session::config::{BrowserSession, CookieContentSecurity, TtlExtensionPolicy};
+use actix_session::SessionMiddleware;
+use actix_web::cookie::Key;
 use actix_web::dev::Server;
+use actix_web::middleware::ErrorHandlers;
 use actix_web::{web, App, HttpServer};
+use actix_web_flash_messages::storage::CookieMessageStore;
+use actix_web_flash_messages::FlashMessagesFramework;
+use actix_web_lab::middleware::from_fn;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::postgres::PgPoolOptions;
 use sqlx::PgPool;
 use std::net::TcpListener;
+use std::sync::Arc;
 use tracing_actix_web::TracingLogger;
 
 fn run(
     listener: TcpListener,
     database_pool: PgPool,
-    email_client: EmailClient,
-    base_url: String,
+    email_client: Arc<dyn EmailSender>,
+    application_settings: &ApplicationSettings,
+    action_link_signer: ActionLinkSigner,
+    mailbox: Option<Mailbox>,
+    health_check_settings: HealthCheckSettings,
 ) -> Result<Server, std::io::Error> {
+    let secret_key = Key::from(application_settings.hmac_secret.expose_secret().as_bytes());
+    let message_store = CookieMessageStore::builder(secret_key.clone()).build();
+    let message_framework = FlashMessagesFramework::builder(message_store).build();
+    let session_store = PgSessionStore::new(database_pool.clone());
+    let session_lifecycle = BrowserSession::default()
+        .state_ttl(actix_web::cookie::time::Duration::seconds(
+            application_settings.session_idle_timeout().as_secs() as i64,
+        ))
+        .state_ttl_extension_policy(TtlExtensionPolicy::OnEveryRequest);
     let database_connection = web::Data::new(database_pool);
-    let email_client = web::Data::new(email_client);
-    let base_url = web::Data::new(ApplicationBaseUrl(base_url));
+    let email_client: web::Data<dyn EmailSender> = web::Data::from(email_client);
+    let base_url = web::Data::new(ApplicationBaseUrl(application_settings.base_url.clone()));
+    let subscription_token_ttl = web::Data::new(SubscriptionTokenTtl(
+        application_settings.subscription_token_ttl(),
+    ));
+    let subscription_token_length = web::Data::new(SubscriptionTokenLength(
+        application_settings.subscription_token_length,
+    ));
+    let privacy_policy_version = web::Data::new(PrivacyPolicyVersion(
+        application_settings.privacy_policy_version.clone(),
+    ));
+    let action_link_signer = web::Data::new(action_link_signer);
+    let mailbox = mailbox.map(web::Data::new);
+    let health_check_settings = web::Data::new(health_check_settings);
+    let serve_swagger_ui = application_settings.swagger_ui;
+    let metrics_bearer_token = application_settings
+        .metrics_bearer_token
+        .clone()
+        .map(|token| web::Data::new(MetricsBearerToken(token)));
 
     let server = HttpServer::new(move || {
         App::new()
+            .wrap(message_framework.clone())
+            .wrap(
+                SessionMiddleware::builder(session_store.clone(), secret_key.clone())
+                    .cookie_content_security(CookieContentSecurity::Signed)
+                    .session_lifecycle(session_lifecycle.clone())
+                    .build(),
+            )
+            .wrap(from_fn(record_http_metrics))
             .wrap(TracingLogger::default())
+            .wrap(ErrorHandlers::new().default_handler(render_problem_details))
             .route("/health", web::get().to(health_check))
+            .route("/health/live", web::get().to(liveness))
+            .route("/health/ready", web::get().to(readiness))
+            .route("/login", web::get().to(login_form))
+            .route("/login", web::post().to(login))
             .route("/subscribe", web::post().to(subscribe))
             .route("/subscriptions/confirm", web::get().to(confirm))
+            .route(
+                "/subscriptions/confirm/resend",
+                web::post().to(resend_confirmation),
+            )
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(unsubscribe_form),
+            )
+            .route("/subscriptions/unsubscribe", web::post().to(unsubscribe))
+            .service(
+                web::scope("/admin")
+                    .wrap(from_fn(reject_anonymous_users))
+                    .route("/dashboard", web::get().to(admin_dashboard))
+                    .route("/newsletters", web::get().to(publish_newsletter_form))
+                    .route("/newsletters", web::post().to(publish_newsletter))
+                    .route("/logout", web::post().to(log_out))
+                    .route("/subscribers/timeline", web::get().to(subscriber_timeline))
+                    .route(
+                        "/subscribers/consent",
+                        web::get().to(subscriber_consent_export),
+                    ),
+            )
+            .service(
+                web::scope("/api/v1")
+                    .route("/openapi.json", web::get().to(openapi_json))
+                    .route("/subscriptions", web::post().to(subscribe))
+                    .route(
+                        "/subscriptions/{subscription_id}",
+                        web::get().to(get_subscription_by_id),
+                    )
+                    .service(
+                        web::scope("/admin")
+                            .wrap(from_fn(reject_anonymous_users))
+                            .route("/newsletters", web::post().to(publish_newsletter))
+                            .route(
+                                "/subscribers/consent",
+                                web::get().to(subscriber_consent_export),
+                            ),
+                    )
+                    .configure(|cfg| {
+                        if serve_swagger_ui {
+                            cfg.route("/docs", web::get().to(swagger_ui));
+                        }
+                    }),
+            )
+            .configure(|cfg| {
+                if let Some(metrics_bearer_token) = &metrics_bearer_token {
+                    cfg.service(
+                        web::resource("/metrics")
+                            .app_data(metrics_bearer_token.clone())
+                            .route(web::get().to(metrics)),
+                    );
+                }
+            })
+            .configure(|cfg| {
+                if let Some(mailbox) = &mailbox {
+                    cfg.service(
+                        web::scope("/dev/mailbox")
+                            .app_data(mailbox.clone())
+                            .route("", web::get().to(dev_mailbox))
+                            .route("/{id}", web::get().to(dev_mailbox_email)),
+                    );
+                }
+            })
             .app_data(database_connection.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(subscription_token_ttl.clone())
+            .app_data(subscription_token_length.clone())
+            .app_data(action_link_signer.clone())
+            .app_data(privacy_policy_version.clone())
+            .app_data(health_check_settings.clone())
     })
     .listen(listener)?
     .run();
@@ -46,20 +178,19 @@ pub struct Application {
 }
 
 impl Application {
-    pub async fn build(configuration: Settings) -> Result<Self, std::io::Error> {
+    /// `email_client` is taken rather than built here so that the delivery
+    /// worker can share it, along with its send rate limit.
+    pub async fn build(
+        configuration: Settings,
+        email_client: Arc<dyn EmailSender>,
+    ) -> Result<Self, std::io::Error> {
         let database_pool = get_connection(&configuration.database_settings);
-        let sender_email = configuration
-            .email_client
-            .sender()
-            .expect("Invalid sender email address");
-
-        let timeout = configuration.email_client.timeout();
-        let email_client = EmailClient::new(
-            configuration.email_client.base_url,
-            sender_email,
-            configuration.email_client.api_token,
-            timeout,
-        );
+        // Captured emails may contain live confirmation links: only expose
+        // them when running locally.
+        let mailbox = match configuration.environment {
+            Environment::Local => configuration.email_client.mailbox(),
+            Environment::Production => None,
+        };
 
         let address = format!(
             "{}:{}",
@@ -71,7 +202,10 @@ impl Application {
             listener,
             database_pool,
             email_client,
-            configuration.application_settings.base_url,
+            &configuration.application_settings,
+            configuration.action_links.signer(),
+            mailbox,
+            configuration.health_check,
         )?;
 
         Ok(Self { port, server })
@@ -87,3 +221,11 @@ impl Application {
 }
 
 pub struct ApplicationBaseUrl(pub String);
+
+pub struct MetricsBearerToken(pub Secret<String>);
+
+pub struct SubscriptionTokenTtl(pub std::time::Duration);
+
+pub struct SubscriptionTokenLength(pub usize);
+
+pub struct PrivacyPolicyVersion(pub String);
@@ -0,0 +1,86 @@
+use actix_web::http::header::LOCATION;
+use actix_web::HttpResponse;
+
+pub fn e500<T>(error: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    actix_web::error::ErrorInternalServerError(error)
+}
+
+pub fn see_other(location: &str) -> HttpResponse {
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, location))
+        .finish()
+}
+
+pub fn e400<T>(error: T) -> actix_web::Error
+where
+    T: std::fmt::Debug + std::fmt::Display + 'static,
+{
+    actix_web::error::ErrorBadRequest(error)
+}
+
+/// Formats an error followed by every error in its source chain, for `Debug`
+/// implementations of errors that reach the logs.
+pub fn error_chain_fmt(
+    error: &impl std::error::Error,
+    f: &mut std::fmt::Formatter<'_>,
+) -> std::fmt::Result {
+    writeln!(f, "{}", error)?;
+    let mut current = error.source();
+    while let Some(cause) = current {
+        writeln!(f, "Caused by:\n\t{}", cause)?;
+        current = cause.source();
+    }
+    Ok(())
+}
+
+/// Escapes text for use in HTML content and attribute values.
+pub fn escape_html(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::{error_chain_fmt, escape_html};
+
+    #[derive(thiserror::Error)]
+    #[error("Failed to subscribe.")]
+    struct OuterError(#[source] std::io::Error);
+
+    impl std::fmt::Debug for OuterError {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            error_chain_fmt(self, f)
+        }
+    }
+
+    #[test]
+    fn the_whole_source_chain_is_formatted() {
+        let error = OuterError(std::io::Error::other("connection reset"));
+
+        assert_eq!(
+            "Failed to subscribe.\nCaused by:\n\tconnection reset\n",
+            format!("{:?}", error)
+        );
+    }
+
+    #[test]
+    fn html_special_characters_are_escaped() {
+        assert_eq!(
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;",
+            escape_html(r#"<a href="x">Tom & Jerry's</a>"#)
+        );
+    }
+}
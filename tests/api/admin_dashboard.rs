@@ -0,0 +1,77 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+use secrecy::Secret;
+use uuid::Uuid;
+use zero2prod::authentication::upsert_user;
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_access_the_admin_dashboard() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.get_admin_dashboard().await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn basic_auth_clients_get_401_instead_of_a_redirect() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .api_client
+        .get(format!("{}/admin/dashboard", test_app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(401, response.status().as_u16());
+    assert_eq!(
+        r#"Basic realm="admin""#,
+        response.headers()["WWW-Authenticate"]
+    );
+}
+
+#[tokio::test]
+async fn logout_clears_session_state() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .post_login(&serde_json::json!({
+            "username": &test_app.test_user.username,
+            "password": &test_app.test_user.password
+        }))
+        .await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let html_page = test_app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", test_app.test_user.username)));
+
+    let response = test_app.post_logout().await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = test_app.get_login_html().await;
+    assert!(html_page.contains(r#"<p><i>You have successfully logged out.</i></p>"#));
+
+    let response = test_app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_username_is_escaped_on_the_dashboard() {
+    let test_app = spawn_app().await;
+    let username = "<script>alert('hi')</script>";
+    let password = Uuid::new_v4().to_string();
+    upsert_user(username, Secret::new(password.clone()), &test_app.db_poll)
+        .await
+        .unwrap();
+
+    test_app
+        .post_login(&serde_json::json!({
+            "username": username,
+            "password": password,
+        }))
+        .await;
+
+    let html_page = test_app.get_admin_dashboard_html().await;
+    assert!(!html_page.contains(username));
+    assert!(html_page.contains("&lt;script&gt;"));
+}
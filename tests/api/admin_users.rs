@@ -0,0 +1,82 @@
+use crate::helpers::spawn_app;
+use secrecy::Secret;
+use uuid::Uuid;
+use zero2prod::authentication::{upsert_user, validate_credentials, Credentials};
+
+fn credentials(username: &str, password: &str) -> Credentials {
+    Credentials {
+        username: username.into(),
+        password: Secret::new(password.into()),
+    }
+}
+
+#[tokio::test]
+async fn an_admin_created_from_the_command_line_can_authenticate() {
+    let test_app = spawn_app().await;
+    let password = Uuid::new_v4().to_string();
+
+    let user_id = upsert_user(
+        "new-admin",
+        Secret::new(password.clone()),
+        &test_app.db_poll,
+    )
+    .await
+    .unwrap();
+
+    let authenticated =
+        validate_credentials(credentials("new-admin", &password), &test_app.db_poll)
+            .await
+            .unwrap();
+    assert_eq!(user_id, authenticated);
+}
+
+#[tokio::test]
+async fn creating_an_existing_admin_again_replaces_its_password() {
+    let test_app = spawn_app().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    let user_id = upsert_user(
+        &test_app.test_user.username,
+        Secret::new(new_password.clone()),
+        &test_app.db_poll,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(test_app.test_user.user_id, user_id);
+    let old_password = credentials(&test_app.test_user.username, &test_app.test_user.password);
+    assert!(validate_credentials(old_password, &test_app.db_poll)
+        .await
+        .is_err());
+    let new_password = credentials(&test_app.test_user.username, &new_password);
+    assert!(validate_credentials(new_password, &test_app.db_poll)
+        .await
+        .is_ok());
+}
+
+#[tokio::test]
+async fn admin_passwords_must_not_be_too_short() {
+    let test_app = spawn_app().await;
+
+    let outcome = upsert_user("new-admin", Secret::new("short".into()), &test_app.db_poll).await;
+
+    assert!(outcome.is_err());
+    let users = sqlx::query!("SELECT user_id FROM users WHERE username = 'new-admin'")
+        .fetch_all(&test_app.db_poll)
+        .await
+        .unwrap();
+    assert!(users.is_empty());
+}
+
+#[tokio::test]
+async fn no_admin_account_is_seeded_by_the_migrations() {
+    let test_app = spawn_app().await;
+
+    let users = sqlx::query!("SELECT username FROM users")
+        .fetch_all(&test_app.db_poll)
+        .await
+        .unwrap();
+
+    assert_eq!(1, users.len());
+    assert_eq!(test_app.test_user.username, users[0].username);
+}
@@ -0,0 +1,175 @@
+use crate::helpers::{create_confirmed_subscriber, spawn_app, spawn_app_with, TestApp};
+use serde_json::{json, Value};
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+/// Checks `body` against the schema the OpenAPI document gives for the
+/// `status` response of `method` on `path`.
+fn assert_matches_contract(document: &Value, method: &str, path: &str, status: u16, body: &Value) {
+    let content = &document["paths"][path][method]["responses"][status.to_string()]["content"];
+    let (_, media_type) = content
+        .as_object()
+        .and_then(|content| content.iter().next())
+        .unwrap_or_else(|| panic!("{} {} documents no body for {}.", method, path, status));
+    let mut schema = media_type["schema"].clone();
+    schema["components"] = document["components"].clone();
+
+    let validator = jsonschema::draft202012::new(&schema).expect("The schema is invalid.");
+    if let Err(error) = validator.validate(body) {
+        panic!(
+            "The {} response of {} {} does not match the contract: {}\n{}",
+            status, method, path, error, body
+        );
+    }
+}
+
+async fn mount_email_server(test_app: &TestApp) {
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+}
+
+#[tokio::test]
+async fn the_openapi_document_describes_the_api() {
+    let test_app = spawn_app().await;
+
+    let document = test_app.get_openapi_document().await;
+
+    assert!(document["openapi"].as_str().unwrap().starts_with("3.1"));
+    for path in [
+        "/api/v1/subscriptions",
+        "/api/v1/subscriptions/{subscription_id}",
+        "/api/v1/admin/newsletters",
+        "/api/v1/admin/subscribers/consent",
+    ] {
+        assert!(
+            document["paths"].get(path).is_some(),
+            "{} is not documented.",
+            path
+        );
+    }
+    assert_eq!(
+        "basic",
+        document["components"]["securitySchemes"]["basic_auth"]["scheme"]
+    );
+}
+
+#[tokio::test]
+async fn creating_and_fetching_a_subscription_follows_the_contract() {
+    let test_app = spawn_app().await;
+    mount_email_server(&test_app).await;
+    let document = test_app.get_openapi_document().await;
+
+    let response = test_app
+        .post_api_subscription(&json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com"
+        }))
+        .await;
+
+    assert_eq!(201, response.status().as_u16());
+    let location = response.headers()["Location"].to_str().unwrap().to_owned();
+    assert!(location.starts_with("/api/v1/subscriptions/"));
+    let body: Value = response.json().await.unwrap();
+    assert_matches_contract(&document, "post", "/api/v1/subscriptions", 201, &body);
+
+    let response = test_app.get_subscription(&location).await;
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_matches_contract(
+        &document,
+        "get",
+        "/api/v1/subscriptions/{subscription_id}",
+        200,
+        &body,
+    );
+}
+
+#[tokio::test]
+async fn validation_problems_follow_the_contract() {
+    let test_app = spawn_app().await;
+    let document = test_app.get_openapi_document().await;
+
+    let response = test_app
+        .post_api_subscription(&json!({ "name": "", "email": "not-an-email" }))
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_matches_contract(&document, "post", "/api/v1/subscriptions", 400, &body);
+}
+
+#[tokio::test]
+async fn the_consent_export_follows_the_contract() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let document = test_app.get_openapi_document().await;
+
+    let response = test_app
+        .api_client
+        .get(format!(
+            "{}/api/v1/admin/subscribers/consent",
+            test_app.address
+        ))
+        .query(&[("email", "ursula_le_guin@gmail.com")])
+        .basic_auth(
+            &test_app.test_user.username,
+            Some(&test_app.test_user.password),
+        )
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_matches_contract(
+        &document,
+        "get",
+        "/api/v1/admin/subscribers/consent",
+        200,
+        &body,
+    );
+}
+
+#[tokio::test]
+async fn admin_endpoints_require_credentials() {
+    let test_app = spawn_app().await;
+    let document = test_app.get_openapi_document().await;
+
+    let response = test_app
+        .api_client
+        .post(format!("{}/api/v1/admin/newsletters", test_app.address))
+        .json(&json!({
+            "title": "Newsletter title",
+            "content": { "text": "Body", "html": "<p>Body</p>" }
+        }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(401, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_matches_contract(&document, "post", "/api/v1/admin/newsletters", 401, &body);
+}
+
+#[tokio::test]
+async fn the_swagger_ui_is_only_served_when_enabled() {
+    let enabled = spawn_app_with(|c| c.application_settings.swagger_ui = true).await;
+    let disabled = spawn_app_with(|c| c.application_settings.swagger_ui = false).await;
+
+    let response = reqwest::get(format!("{}/api/v1/docs", enabled.address))
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert!(response
+        .text()
+        .await
+        .unwrap()
+        .contains("/api/v1/openapi.json"));
+
+    let response = reqwest::get(format!("{}/api/v1/docs", disabled.address))
+        .await
+        .unwrap();
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,178 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app_with, TestApp};
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+const POLICY_VERSION: &str = "2023-11-01";
+
+async fn spawn_app() -> TestApp {
+    let test_app = spawn_app_with(|c| {
+        c.application_settings.privacy_policy_version = POLICY_VERSION.into();
+    })
+    .await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    test_app
+}
+
+async fn subscribe_from_footer(test_app: &TestApp) {
+    test_app
+        .api_client
+        .post(format!("{}/subscribe", test_app.address))
+        .header("X-Forwarded-For", "203.0.113.7")
+        .header("User-Agent", "Browser")
+        .form(&[("name", "le guin"), ("email", EMAIL), ("source", "footer")])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn confirm_from_mail_client(test_app: &TestApp) {
+    let email_request = &test_app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_link = test_app.get_confirmation_link(email_request);
+    test_app
+        .api_client
+        .get(confirmation_link.html_confirmation_link)
+        .header("X-Forwarded-For", "198.51.100.2")
+        .header("User-Agent", "Mail client")
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn subscribing_records_the_consent() {
+    let test_app = spawn_app().await;
+
+    subscribe_from_footer(&test_app).await;
+
+    let consent = sqlx::query!(
+        r#"
+        SELECT
+            privacy_policy_version,
+            form_source,
+            consent_ip_address,
+            consent_user_agent,
+            confirmed_at
+        FROM consent_records
+        "#
+    )
+    .fetch_one(&test_app.db_poll)
+    .await
+    .unwrap();
+    assert_eq!(POLICY_VERSION, consent.privacy_policy_version);
+    assert_eq!(Some("footer"), consent.form_source.as_deref());
+    assert_eq!(Some("203.0.113.7"), consent.consent_ip_address.as_deref());
+    assert_eq!(Some("Browser"), consent.consent_user_agent.as_deref());
+    assert_eq!(None, consent.confirmed_at);
+}
+
+#[tokio::test]
+async fn the_referer_is_the_form_source_when_none_is_given() {
+    let test_app = spawn_app().await;
+
+    test_app
+        .api_client
+        .post(format!("{}/subscribe", test_app.address))
+        .header("Referer", "https://example.com/blog/post")
+        .form(&[("name", "le guin"), ("email", EMAIL)])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let consent = sqlx::query!("SELECT form_source FROM consent_records")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    assert_eq!(
+        Some("https://example.com/blog/post"),
+        consent.form_source.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn confirming_completes_the_consent() {
+    let test_app = spawn_app().await;
+    subscribe_from_footer(&test_app).await;
+
+    confirm_from_mail_client(&test_app).await;
+
+    let consent = sqlx::query!(
+        r#"
+        SELECT
+            consented_at,
+            confirmed_at,
+            confirmation_ip_address,
+            confirmation_user_agent
+        FROM consent_records
+        "#
+    )
+    .fetch_one(&test_app.db_poll)
+    .await
+    .unwrap();
+    let confirmed_at = consent
+        .confirmed_at
+        .expect("The consent was not confirmed.");
+    assert!(confirmed_at >= consent.consented_at);
+    assert_eq!(
+        Some("198.51.100.2"),
+        consent.confirmation_ip_address.as_deref()
+    );
+    assert_eq!(
+        Some("Mail client"),
+        consent.confirmation_user_agent.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_export_consent_records() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.get_subscriber_consent_export(EMAIL).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn the_export_contains_the_consent_records_of_a_subscriber() {
+    let test_app = spawn_app().await;
+    subscribe_from_footer(&test_app).await;
+    confirm_from_mail_client(&test_app).await;
+    test_app.login_as_test_user().await;
+
+    let response = test_app.get_subscriber_consent_export(EMAIL).await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert!(response.headers()["Content-Disposition"]
+        .to_str()
+        .unwrap()
+        .starts_with("attachment"));
+    let export: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(EMAIL, export["email"]);
+    assert_eq!("confirmed", export["status"]);
+    let consents = export["consents"].as_array().unwrap();
+    assert_eq!(1, consents.len());
+    assert_eq!(POLICY_VERSION, consents[0]["privacy_policy_version"]);
+    assert_eq!("footer", consents[0]["form_source"]);
+    assert_eq!("203.0.113.7", consents[0]["consent_ip_address"]);
+    assert_eq!("198.51.100.2", consents[0]["confirmation_ip_address"]);
+    assert!(consents[0]["confirmed_at"].is_string());
+}
+
+#[tokio::test]
+async fn the_export_of_an_unknown_subscriber_is_a_404() {
+    let test_app = spawn_app().await;
+    test_app.login_as_test_user().await;
+
+    let response = test_app.get_subscriber_consent_export(EMAIL).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
@@ -0,0 +1,75 @@
+use crate::helpers::{spawn_app, spawn_app_with, TestApp};
+use uuid::Uuid;
+use zero2prod::configuration::{CaptureSettings, EmailBackendKind, Environment};
+
+async fn spawn_app_with_capture_backend(environment: Environment) -> TestApp {
+    spawn_app_with(|c| {
+        c.environment = environment;
+        c.email_client.kind = EmailBackendKind::Capture;
+        c.email_client.capture = Some(CaptureSettings {
+            directory: std::env::temp_dir()
+                .join(Uuid::new_v4().to_string())
+                .to_string_lossy()
+                .into_owned(),
+        });
+    })
+    .await
+}
+
+#[tokio::test]
+async fn captured_confirmation_emails_are_listed_in_the_dev_mailbox() {
+    let test_app = spawn_app_with_capture_backend(Environment::Local).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let response = test_app.subscribe_request(body.into()).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let response = test_app.get_dev_mailbox().await;
+    assert_eq!(200, response.status().as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("ursula_le_guin@gmail.com"));
+    assert!(html_page.contains("Welcome!"));
+}
+
+#[tokio::test]
+async fn a_captured_email_is_rendered_with_a_clickable_confirmation_link() {
+    let test_app = spawn_app_with_capture_backend(Environment::Local).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    test_app.subscribe_request(body.into()).await;
+
+    let html_page = test_app.get_dev_mailbox().await.text().await.unwrap();
+    let email_path = html_page
+        .split("href=\"")
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap();
+    let response = test_app
+        .api_client
+        .get(format!("{}{}", test_app.address, email_path))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page
+        .contains(r#"<a href="http://127.0.0.1/subscriptions/confirm?subscription_token="#));
+}
+
+#[tokio::test]
+async fn the_dev_mailbox_is_not_mounted_outside_the_local_environment() {
+    let test_app = spawn_app_with_capture_backend(Environment::Production).await;
+
+    let response = test_app.get_dev_mailbox().await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_dev_mailbox_is_not_mounted_for_other_email_backends() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.get_dev_mailbox().await;
+
+    assert_eq!(404, response.status().as_u16());
+}
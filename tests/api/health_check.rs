@@ -1,4 +1,8 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{spawn_app, spawn_app_with};
+use sqlx::{Connection, Executor, PgConnection};
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::configuration::{DependencyCheck, Settings};
 
 #[tokio::test]
 async fn health_check_returns_expected_result() {
@@ -7,7 +11,7 @@ async fn health_check_returns_expected_result() {
     let client = reqwest::Client::new();
 
     let response = client
-        .get(&format!("{}/health", &test_app.address))
+        .get(format!("{}/health", &test_app.address))
         .send()
         .await
         .expect("Failed to execute request");
@@ -15,3 +19,153 @@ async fn health_check_returns_expected_result() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[tokio::test]
+async fn liveness_reports_the_process_as_up() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .api_client
+        .get(format!("{}/health/live", &test_app.address))
+        .send()
+        .await
+        .expect("Failed to execute request");
+
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(serde_json::json!({ "status": "up" }), report);
+}
+
+#[tokio::test]
+async fn readiness_reports_every_required_dependency() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.get_readiness().await;
+
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("up", report["status"]);
+    for check in ["database", "migrations"] {
+        assert_eq!("up", report["checks"][check]["status"]);
+        assert_eq!(true, report["checks"][check]["required"]);
+        assert!(report["checks"][check]["latency_ms"].is_u64());
+    }
+    // The email provider is not probed unless configured to.
+    assert!(report["checks"].get("email_client").is_none());
+}
+
+#[tokio::test]
+async fn readiness_fails_when_a_migration_is_pending() {
+    let test_app = spawn_app().await;
+    let latest_version: i64 =
+        sqlx::query_scalar("DELETE FROM _sqlx_migrations WHERE version = (SELECT max(version) FROM _sqlx_migrations) RETURNING version")
+            .fetch_one(&test_app.db_poll)
+            .await
+            .unwrap();
+
+    let response = test_app.get_readiness().await;
+
+    assert_eq!(503, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("down", report["status"]);
+    assert_eq!("up", report["checks"]["database"]["status"]);
+    assert_eq!("down", report["checks"]["migrations"]["status"]);
+    // Which migrations are missing is only logged.
+    assert_eq!(
+        "The database schema is not up to date.",
+        report["checks"]["migrations"]["detail"]
+    );
+    assert!(!report.to_string().contains(&latest_version.to_string()));
+}
+
+#[tokio::test]
+async fn readiness_fails_when_the_database_is_gone() {
+    let test_app = spawn_app().await;
+    let database_name: String = sqlx::query_scalar("SELECT current_database()")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    test_app.db_poll.close().await;
+    let configuration = Settings::new().expect("Failed to read configuration");
+    let mut connection = PgConnection::connect_with(
+        &configuration
+            .database_settings
+            .get_connection_options_without_db(),
+    )
+    .await
+    .unwrap();
+    connection
+        .execute(format!(r#"DROP DATABASE "{}" WITH (FORCE);"#, database_name).as_str())
+        .await
+        .unwrap();
+
+    let response = test_app.get_readiness().await;
+
+    assert_eq!(503, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("down", report["status"]);
+    assert_eq!("down", report["checks"]["database"]["status"]);
+    assert_eq!(
+        "Postgres is unreachable.",
+        report["checks"]["database"]["detail"]
+    );
+    assert!(!report.to_string().contains(&database_name));
+}
+
+#[tokio::test]
+async fn readiness_fails_when_a_required_email_provider_is_down() {
+    let test_app =
+        spawn_app_with(|c| c.health_check.email_client = DependencyCheck::Required).await;
+    Mock::given(path("/v3/account"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.get_readiness().await;
+
+    assert_eq!(503, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("down", report["checks"]["email_client"]["status"]);
+    assert_eq!(true, report["checks"]["email_client"]["required"]);
+}
+
+#[tokio::test]
+async fn an_optional_email_provider_being_down_is_only_reported() {
+    let test_app =
+        spawn_app_with(|c| c.health_check.email_client = DependencyCheck::Optional).await;
+    Mock::given(path("/v3/account"))
+        .and(method("GET"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.get_readiness().await;
+
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("up", report["status"]);
+    assert_eq!("down", report["checks"]["email_client"]["status"]);
+    assert_eq!(false, report["checks"]["email_client"]["required"]);
+}
+
+#[tokio::test]
+async fn readiness_checks_a_reachable_email_provider() {
+    let test_app =
+        spawn_app_with(|c| c.health_check.email_client = DependencyCheck::Required).await;
+    Mock::given(path("/v3/account"))
+        .and(method("GET"))
+        .and(header_exists("api-key"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.get_readiness().await;
+
+    assert_eq!(200, response.status().as_u16());
+    let report: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("up", report["checks"]["email_client"]["status"]);
+}
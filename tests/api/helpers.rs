@@ -1,8 +1,15 @@
 use once_cell::sync::Lazy;
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
+use std::sync::Arc;
 use uuid::Uuid;
-use wiremock::{MockServer, Request};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, Request, ResponseTemplate};
+use zero2prod::action_links::ActionLinkSigner;
+use zero2prod::authentication::compute_password_hash;
 use zero2prod::configuration::{DatabaseSettings, Settings};
+use zero2prod::email_client::EmailSender;
+use zero2prod::issue_delivery_worker::{try_execute_task, ExecutionOutcome, UnsubscribeLinks};
 use zero2prod::startup::{get_connection, Application};
 use zero2prod::telemetry::{get_subscriber, init_subscriber};
 
@@ -22,11 +29,54 @@ static TRACING: Lazy<()> = Lazy::new(|| {
 pub struct TestApp {
     pub address: String,
     pub port: u16,
+    pub base_url: String,
     pub db_poll: PgPool,
     pub email_server: MockServer,
+    pub test_user: TestUser,
+    pub api_client: reqwest::Client,
+    pub email_client: Arc<dyn EmailSender>,
+    pub action_link_signer: ActionLinkSigner,
+    pub unsubscribe_links: UnsubscribeLinks,
+    pub metrics_bearer_token: Option<String>,
+}
+
+pub struct TestUser {
+    pub user_id: Uuid,
+    pub username: String,
+    pub password: String,
+}
+
+impl TestUser {
+    pub fn generate() -> Self {
+        Self {
+            user_id: Uuid::new_v4(),
+            username: Uuid::new_v4().to_string(),
+            password: Uuid::new_v4().to_string(),
+        }
+    }
+
+    async fn store(&self, db_pool: &PgPool) {
+        let password_hash = compute_password_hash(Secret::new(self.password.clone()))
+            .expect("Failed to hash the test user password.");
+
+        sqlx::query!(
+            "INSERT INTO users (user_id, username, password_hash) VALUES ($1, $2, $3)",
+            self.user_id,
+            self.username,
+            password_hash.expose_secret(),
+        )
+        .execute(db_pool)
+        .await
+        .expect("Failed to store test user.");
+    }
 }
 
 pub async fn spawn_app() -> TestApp {
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns the application after letting the test tweak the configuration.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     let email_server = MockServer::start().await;
@@ -36,25 +86,53 @@ pub async fn spawn_app() -> TestApp {
         c.database_settings.database_name = Uuid::new_v4().to_string();
         c.application_settings.port = 0;
         c.email_client.base_url = email_server.uri();
+        c.email_client.retry.base_delay_milliseconds = 1;
+        c.email_client.retry.max_delay_milliseconds = 10;
+        configure(&mut c);
 
         c
     };
 
     configure_database(&configuration.database_settings).await;
 
-    let application = Application::build(configuration.clone())
+    let email_client = configuration.email_client.clone().client();
+    let application = Application::build(configuration.clone(), email_client.clone())
         .await
         .expect("Failed to build application.");
     let application_port = application.port();
 
-    let _ = tokio::spawn(application.run_until_stopped());
+    tokio::spawn(application.run_until_stopped());
 
-    TestApp {
+    let api_client = reqwest::Client::builder()
+        .redirect(reqwest::redirect::Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap();
+
+    let test_app = TestApp {
         address: format!("http://127.0.0.1:{}", application_port),
         port: application_port,
+        base_url: configuration.application_settings.base_url.clone(),
         db_poll: get_connection(&configuration.database_settings),
         email_server,
-    }
+        test_user: TestUser::generate(),
+        api_client,
+        email_client,
+        action_link_signer: configuration.action_links.signer(),
+        metrics_bearer_token: configuration
+            .application_settings
+            .metrics_bearer_token
+            .as_ref()
+            .map(|token| token.expose_secret().clone()),
+        unsubscribe_links: UnsubscribeLinks {
+            base_url: configuration.application_settings.base_url.clone(),
+            signer: configuration.action_links.signer(),
+            ttl: configuration.action_links.unsubscribe_link_ttl(),
+        },
+    };
+    test_app.test_user.store(&test_app.db_poll).await;
+
+    test_app
 }
 
 async fn configure_database(config: &DatabaseSettings) -> PgPool {
@@ -80,6 +158,21 @@ async fn configure_database(config: &DatabaseSettings) -> PgPool {
 }
 
 impl TestApp {
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_poll,
+                self.email_client.as_ref(),
+                &self.unsubscribe_links,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
     pub async fn subscribe_request(&self, body: String) -> reqwest::Response {
         reqwest::Client::new()
             .post(format!("{}/subscribe", self.address))
@@ -90,6 +183,228 @@ impl TestApp {
             .expect("Failed to send the error")
     }
 
+    pub async fn post_subscription_json(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscribe", self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_subscription(&self, body: &serde_json::Value) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/api/v1/subscriptions", self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_openapi_document(&self) -> serde_json::Value {
+        self.api_client
+            .get(format!("{}/api/v1/openapi.json", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .error_for_status()
+            .unwrap()
+            .json()
+            .await
+            .unwrap()
+    }
+
+    pub async fn get_readiness(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/health/ready", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_metrics(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/metrics", self.address))
+            .bearer_auth(self.metrics_bearer_token.as_deref().unwrap_or_default())
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// The current value of one series, e.g. `http_requests_total{...}`.
+    /// Series nobody has touched yet are absent and read as zero.
+    pub async fn get_metric(&self, series: &str) -> f64 {
+        let body = self.get_metrics().await.text().await.unwrap();
+
+        body.lines()
+            .find_map(|line| {
+                let (name, value) = line.rsplit_once(' ')?;
+                (name == series).then(|| value.parse().unwrap())
+            })
+            .unwrap_or(0.0)
+    }
+
+    pub async fn get_subscription(&self, location: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}{}", self.address, location))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletters(&self, body: serde_json::Value) -> reqwest::Response {
+        self.post_newsletters_with_idempotency_key(body, &Uuid::new_v4().to_string())
+            .await
+    }
+
+    pub async fn post_newsletters_with_idempotency_key(
+        &self,
+        body: serde_json::Value,
+        idempotency_key: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!("{}/api/v1/admin/newsletters", self.address))
+            .basic_auth(&self.test_user.username, Some(&self.test_user.password))
+            .header("Idempotency-Key", idempotency_key)
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_publish_newsletter_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/admin/newsletters", self.address))
+            .header("Accept", "text/html")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_publish_newsletter<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/newsletters", self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_login<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/login", self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_login_html(&self) -> String {
+        self.api_client
+            .get(format!("{}/login", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn login_as_test_user(&self) {
+        self.post_login(&serde_json::json!({
+            "username": &self.test_user.username,
+            "password": &self.test_user.password,
+        }))
+        .await;
+    }
+
+    pub async fn get_admin_dashboard(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/dashboard", self.address))
+            .header("Accept", "text/html")
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_dashboard_html(&self) -> String {
+        self.get_admin_dashboard().await.text().await.unwrap()
+    }
+
+    pub async fn get_subscriber_timeline(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/timeline", self.address))
+            .header("Accept", "text/html")
+            .query(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_subscriber_consent_export(&self, email: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers/consent", self.address))
+            .header("Accept", "text/html")
+            .query(&[("email", email)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_dev_mailbox(&self) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/dev/mailbox", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_unsubscribe(&self, token: &str) -> reqwest::Response {
+        self.api_client
+            .get(format!("{}/subscriptions/unsubscribe", self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Sends the RFC 8058 one-click request a mail client would send.
+    pub async fn post_unsubscribe(&self, token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/unsubscribe", self.address))
+            .query(&[("token", token)])
+            .form(&[("List-Unsubscribe", "One-Click")])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_resend_confirmation(&self, subscription_token: &str) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/subscriptions/confirm/resend", self.address))
+            .form(&[("subscription_token", subscription_token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout(&self) -> reqwest::Response {
+        self.api_client
+            .post(format!("{}/admin/logout", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub fn get_confirmation_link(&self, email_request: &Request) -> ConfirmationLink {
         let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
 
@@ -111,7 +426,7 @@ impl TestApp {
             confirmation_link
         };
 
-        let html_confirmation_link = get_link(&body["htmlContent"].as_str().unwrap());
+        let html_confirmation_link = get_link(body["htmlContent"].as_str().unwrap());
 
         ConfirmationLink {
             html_confirmation_link,
@@ -122,3 +437,46 @@ impl TestApp {
 pub struct ConfirmationLink {
     pub html_confirmation_link: reqwest::Url,
 }
+
+pub fn assert_is_redirect_to(response: &reqwest::Response, location: &str) {
+    assert_eq!(303, response.status().as_u16());
+    assert_eq!(location, response.headers().get("Location").unwrap());
+}
+
+pub async fn create_unconfirmed_subscriber(test_app: &TestApp) -> ConfirmationLink {
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    let _mock_guard = Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .named("Create unconfirmed subscriber")
+        .expect(1)
+        .mount_as_scoped(&test_app.email_server)
+        .await;
+
+    test_app
+        .subscribe_request(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+
+    test_app.get_confirmation_link(&email_request)
+}
+
+pub async fn create_confirmed_subscriber(test_app: &TestApp) {
+    let confirmation_link = create_unconfirmed_subscriber(test_app).await;
+
+    reqwest::get(confirmation_link.html_confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
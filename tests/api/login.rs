@@ -0,0 +1,83 @@
+use crate::helpers::{assert_is_redirect_to, spawn_app};
+
+#[tokio::test]
+async fn an_error_flash_message_is_set_on_failure() {
+    let test_app = spawn_app().await;
+    let login_body = serde_json::json!({
+        "username": "random-username",
+        "password": "random-password"
+    });
+
+    let response = test_app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/login");
+
+    let html_page = test_app.get_login_html().await;
+    assert!(html_page.contains("<p><i>Authentication failed.</i></p>"));
+
+    let html_page = test_app.get_login_html().await;
+    assert!(!html_page.contains("Authentication failed."));
+}
+
+#[tokio::test]
+async fn redirect_to_admin_dashboard_after_login_success() {
+    let test_app = spawn_app().await;
+    let login_body = serde_json::json!({
+        "username": &test_app.test_user.username,
+        "password": &test_app.test_user.password
+    });
+
+    let response = test_app.post_login(&login_body).await;
+    assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let html_page = test_app.get_admin_dashboard_html().await;
+    assert!(html_page.contains(&format!("Welcome {}", test_app.test_user.username)));
+}
+
+#[tokio::test]
+async fn login_rotates_the_session_key() {
+    let test_app = spawn_app().await;
+
+    test_app.login_as_test_user().await;
+    let first_session = sqlx::query!("SELECT session_key FROM sessions")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+
+    test_app.login_as_test_user().await;
+    let sessions = sqlx::query!("SELECT session_key FROM sessions")
+        .fetch_all(&test_app.db_poll)
+        .await
+        .unwrap();
+
+    assert_eq!(1, sessions.len());
+    assert_ne!(first_session.session_key, sessions[0].session_key);
+}
+
+#[tokio::test]
+async fn sessions_are_stored_in_postgres_with_an_expiry() {
+    let test_app = spawn_app().await;
+
+    test_app.login_as_test_user().await;
+
+    let session = sqlx::query!("SELECT session_state, expires_at FROM sessions")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .expect("Failed to fetch the stored session.");
+
+    assert!(session.session_state.get("user_id").is_some());
+    assert!(session.expires_at > chrono::Utc::now());
+}
+
+#[tokio::test]
+async fn expired_sessions_are_rejected() {
+    let test_app = spawn_app().await;
+    test_app.login_as_test_user().await;
+
+    sqlx::query!("UPDATE sessions SET expires_at = now() - interval '1 second'")
+        .execute(&test_app.db_poll)
+        .await
+        .unwrap();
+
+    let response = test_app.get_admin_dashboard().await;
+    assert_is_redirect_to(&response, "/login");
+}
@@ -1,4 +1,15 @@
+mod admin_dashboard;
+mod admin_users;
+mod api_docs;
+mod consent_records;
+mod dev_mailbox;
 mod health_check;
 mod helpers;
+mod login;
+mod metrics;
+mod newsletter;
+mod problem_details;
+mod subscriber_timeline;
 mod subscription;
 mod subscription_confirm;
+mod subscription_unsubscribe;
@@ -0,0 +1,151 @@
+use crate::helpers::{
+    create_confirmed_subscriber, create_unconfirmed_subscriber, spawn_app, spawn_app_with,
+};
+use chrono::{Duration, Utc};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::action_links::LinkAction;
+
+// Every application spawned by the test suite reports into the same
+// process-wide registry, so tests only assert on how much a series grew.
+
+#[tokio::test]
+async fn metrics_are_exposed_in_the_prometheus_text_format() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.get_metrics().await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        "text/plain; version=0.0.4",
+        response.headers().get("Content-Type").unwrap()
+    );
+    let body = response.text().await.unwrap();
+    assert!(body.contains(r#"db_pool_connections{state="idle"}"#));
+    assert!(body.contains(r#"db_pool_connections{state="in_use"}"#));
+    assert!(body.contains("# TYPE subscriptions_created_total counter"));
+}
+
+#[tokio::test]
+async fn scraping_requires_the_bearer_token() {
+    let test_app = spawn_app().await;
+    let url = format!("{}/metrics", test_app.address);
+
+    let anonymous = test_app.api_client.get(&url).send().await.unwrap();
+    let wrong_token = test_app
+        .api_client
+        .get(&url)
+        .bearer_auth("not-the-token")
+        .send()
+        .await
+        .unwrap();
+
+    for response in [anonymous, wrong_token] {
+        assert_eq!(401, response.status().as_u16());
+        assert_eq!("Bearer", response.headers()["WWW-Authenticate"]);
+        assert!(!response
+            .text()
+            .await
+            .unwrap()
+            .contains("http_requests_total"));
+    }
+}
+
+#[tokio::test]
+async fn metrics_are_not_served_without_a_configured_token() {
+    let test_app = spawn_app_with(|c| c.application_settings.metrics_bearer_token = None).await;
+
+    let response = test_app.get_metrics().await;
+
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn requests_are_counted_by_route_pattern_and_status() {
+    let test_app = spawn_app().await;
+    let matched = r#"http_requests_total{method="GET",route="/api/v1/subscriptions/{subscription_id}",status="404"}"#;
+    let unmatched = r#"http_requests_total{method="GET",route="unmatched",status="404"}"#;
+    let (matched_before, unmatched_before) = (
+        test_app.get_metric(matched).await,
+        test_app.get_metric(unmatched).await,
+    );
+
+    test_app
+        .get_subscription(&format!("/api/v1/subscriptions/{}", uuid::Uuid::new_v4()))
+        .await;
+    test_app.get_subscription("/no/such/page").await;
+
+    assert!(test_app.get_metric(matched).await >= matched_before + 1.0);
+    assert!(test_app.get_metric(unmatched).await >= unmatched_before + 1.0);
+    let body = test_app.get_metrics().await.text().await.unwrap();
+    assert!(!body.contains("/no/such/page"));
+    assert!(body.contains(
+        r#"http_request_duration_seconds_count{method="GET",route="/api/v1/subscriptions/{subscription_id}",status="404"}"#
+    ));
+}
+
+#[tokio::test]
+async fn subscribing_counts_the_subscription_and_the_email_sent() {
+    let test_app = spawn_app().await;
+    let created = "subscriptions_created_total";
+    let sent = r#"emails_sent_total{backend="brevo",outcome="sent"}"#;
+    let (created_before, sent_before) = (
+        test_app.get_metric(created).await,
+        test_app.get_metric(sent).await,
+    );
+
+    create_unconfirmed_subscriber(&test_app).await;
+
+    assert!(test_app.get_metric(created).await >= created_before + 1.0);
+    assert!(test_app.get_metric(sent).await >= sent_before + 1.0);
+}
+
+#[tokio::test]
+async fn failed_emails_are_counted_by_outcome() {
+    let test_app = spawn_app().await;
+    let failed = r#"emails_sent_total{backend="brevo",outcome="transient_failure"}"#;
+    let failed_before = test_app.get_metric(failed).await;
+    Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .subscribe_request("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    assert_eq!(500, response.status().as_u16());
+    assert!(test_app.get_metric(failed).await >= failed_before + 1.0);
+}
+
+#[tokio::test]
+async fn confirmations_and_unsubscriptions_are_counted() {
+    let test_app = spawn_app().await;
+    let confirmed = "subscriptions_confirmed_total";
+    let unsubscribed = "subscriptions_unsubscribed_total";
+    let (confirmed_before, unsubscribed_before) = (
+        test_app.get_metric(confirmed).await,
+        test_app.get_metric(unsubscribed).await,
+    );
+
+    create_confirmed_subscriber(&test_app).await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap()
+        .id;
+    let token = test_app.action_link_signer.sign(
+        subscriber_id,
+        LinkAction::Unsubscribe,
+        Utc::now() + Duration::days(1),
+    );
+    test_app
+        .post_unsubscribe(&token)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    assert!(test_app.get_metric(confirmed).await >= confirmed_before + 1.0);
+    assert!(test_app.get_metric(unsubscribed).await >= unsubscribed_before + 1.0);
+}
@@ -0,0 +1,119 @@
+use crate::helpers::spawn_app;
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::problem_details::{FieldError, ProblemDetails, PROBLEM_JSON};
+
+async fn problem_details(response: reqwest::Response) -> ProblemDetails {
+    assert_eq!(PROBLEM_JSON, response.headers()["Content-Type"]);
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn invalid_subscriber_details_are_reported_field_by_field() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .subscribe_request("name=Ursula%20%3CLe%20Guin%3E&email=not-an-email".into())
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+    let problem = problem_details(response).await;
+    assert_eq!("Bad Request", problem.title);
+    assert_eq!(400, problem.status);
+    let codes: Vec<_> = problem
+        .errors
+        .iter()
+        .map(|error| (error.field.as_str(), error.code.as_str()))
+        .collect();
+    assert_eq!(
+        vec![
+            ("name", "name.forbidden_character"),
+            ("email", "email.invalid")
+        ],
+        codes
+    );
+}
+
+#[tokio::test]
+async fn only_the_invalid_field_is_reported() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .subscribe_request("name=&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    let problem = problem_details(response).await;
+    assert_eq!(
+        vec![FieldError::new(
+            "name",
+            "name.empty",
+            "The subscriber name is empty."
+        )],
+        problem.errors
+    );
+}
+
+#[tokio::test]
+async fn a_malformed_form_is_a_problem_with_a_detail() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.subscribe_request("name=le%20guin".into()).await;
+
+    assert_eq!(400, response.status().as_u16());
+    let problem = problem_details(response).await;
+    assert!(problem.detail.is_some());
+    assert!(problem.errors.is_empty());
+}
+
+#[tokio::test]
+async fn rejected_tokens_are_problems() {
+    let test_app = spawn_app().await;
+
+    let response = reqwest::get(format!(
+        "{}/subscriptions/confirm?subscription_token=unknown",
+        test_app.address
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(401, response.status().as_u16());
+    let problem = problem_details(response).await;
+    assert_eq!(
+        Some("The confirmation token is not valid."),
+        problem.detail.as_deref()
+    );
+}
+
+#[tokio::test]
+async fn unknown_routes_are_problems() {
+    let test_app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/does-not-exist", test_app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(404, response.status().as_u16());
+    assert_eq!(404, problem_details(response).await.status);
+}
+
+#[tokio::test]
+async fn server_errors_do_not_reveal_their_cause() {
+    let test_app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    sqlx::query!("ALTER TABLE consent_records DROP COLUMN form_source")
+        .execute(&test_app.db_poll)
+        .await
+        .unwrap();
+
+    let response = test_app
+        .subscribe_request("name=le%20guin&email=ursula_le_guin%40gmail.com".into())
+        .await;
+
+    assert_eq!(500, response.status().as_u16());
+    let problem = problem_details(response).await;
+    assert_eq!("Internal Server Error", problem.title);
+    assert_eq!(None, problem.detail);
+}
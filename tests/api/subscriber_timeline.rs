@@ -0,0 +1,153 @@
+use crate::helpers::{assert_is_redirect_to, create_unconfirmed_subscriber, spawn_app, TestApp};
+use chrono::{Duration, Utc};
+use wiremock::matchers::any;
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::action_links::LinkAction;
+use zero2prod::domain::{SubscriptionActor, SubscriptionStatus};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+async fn subscribe_from(test_app: &TestApp, ip_address: &str, user_agent: &str) {
+    test_app
+        .api_client
+        .post(format!("{}/subscribe", test_app.address))
+        .header("X-Forwarded-For", ip_address)
+        .header("User-Agent", user_agent)
+        .form(&[("name", "le guin"), ("email", EMAIL)])
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_a_subscriber_timeline() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.get_subscriber_timeline(EMAIL).await;
+
+    assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn every_status_change_is_recorded_with_its_actor_and_client() {
+    let test_app = spawn_app().await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+    reqwest::get(confirmation_link.html_confirmation_link)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    let token = test_app.action_link_signer.sign(
+        subscriber.id,
+        LinkAction::Unsubscribe,
+        Utc::now() + Duration::hours(1),
+    );
+    test_app
+        .api_client
+        .post(format!("{}/subscriptions/unsubscribe", test_app.address))
+        .query(&[("token", token)])
+        .header("X-Forwarded-For", "203.0.113.7")
+        .header("User-Agent", "Mail client")
+        .send()
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    let events = sqlx::query!(
+        r#"
+        SELECT
+            old_status AS "old_status: SubscriptionStatus",
+            new_status AS "new_status: SubscriptionStatus",
+            actor AS "actor: SubscriptionActor",
+            ip_address,
+            user_agent
+        FROM subscription_events
+        WHERE subscriber_id = $1
+        ORDER BY id
+        "#,
+        subscriber.id
+    )
+    .fetch_all(&test_app.db_poll)
+    .await
+    .unwrap();
+
+    let transitions: Vec<_> = events
+        .iter()
+        .map(|event| (event.old_status, event.new_status))
+        .collect();
+    assert_eq!(
+        vec![
+            (None, SubscriptionStatus::PendingConfirmation),
+            (
+                Some(SubscriptionStatus::PendingConfirmation),
+                SubscriptionStatus::Confirmed
+            ),
+            (
+                Some(SubscriptionStatus::Confirmed),
+                SubscriptionStatus::Unsubscribed
+            ),
+        ],
+        transitions
+    );
+    assert!(events
+        .iter()
+        .all(|event| event.actor == SubscriptionActor::Subscriber));
+    assert_eq!(Some("203.0.113.7"), events[2].ip_address.as_deref());
+    assert_eq!(Some("Mail client"), events[2].user_agent.as_deref());
+}
+
+#[tokio::test]
+async fn subscribing_again_with_the_same_status_records_nothing() {
+    let test_app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+
+    subscribe_from(&test_app, "203.0.113.7", "Browser").await;
+    subscribe_from(&test_app, "203.0.113.7", "Browser").await;
+
+    let events = sqlx::query!("SELECT count(*) AS \"count!\" FROM subscription_events")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    assert_eq!(1, events.count);
+}
+
+#[tokio::test]
+async fn the_timeline_lists_the_events_of_a_subscriber() {
+    let test_app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&test_app.email_server)
+        .await;
+    subscribe_from(&test_app, "203.0.113.7", "<script>alert(1)</script>").await;
+    test_app.login_as_test_user().await;
+
+    let response = test_app.get_subscriber_timeline(EMAIL).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains("Current status: pending_confirmation"));
+    assert!(html_page.contains("<td>pending_confirmation</td><td>subscriber</td>"));
+    assert!(html_page.contains("203.0.113.7"));
+    assert!(html_page.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+    assert!(!html_page.contains("<script>"));
+}
+
+#[tokio::test]
+async fn the_timeline_of_an_unknown_subscriber_is_a_404() {
+    let test_app = spawn_app().await;
+    test_app.login_as_test_user().await;
+
+    let response = test_app.get_subscriber_timeline(EMAIL).await;
+
+    assert_eq!(404, response.status().as_u16());
+}
@@ -1,7 +1,9 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{create_confirmed_subscriber, spawn_app};
 use sqlx::query;
-use wiremock::matchers::{method, path};
+use wiremock::matchers::{any, method, path};
 use wiremock::{Mock, ResponseTemplate};
+use zero2prod::domain::SubscriptionStatus;
+use zero2prod::routes::Subscription;
 
 #[tokio::test]
 async fn subscribe_returns_200_for_valid_request_data() {
@@ -34,14 +36,15 @@ async fn subscribe_persists_the_new_subscriber() {
 
     test_app.subscribe_request(body.into()).await;
 
-    let saved = query!("SELECT email, name, status FROM subscriptions")
-        .fetch_one(&test_app.db_poll)
-        .await
-        .expect("Failed to fetch saved subscription.");
+    let saved =
+        query!(r#"SELECT email, name, status AS "status: SubscriptionStatus" FROM subscriptions"#)
+            .fetch_one(&test_app.db_poll)
+            .await
+            .expect("Failed to fetch saved subscription.");
 
     assert_eq!("le guin", saved.name);
     assert_eq!("ursula_le_guin@gmail.com", saved.email);
-    assert_eq!("pending_confirmation", saved.status);
+    assert_eq!(SubscriptionStatus::PendingConfirmation, saved.status);
 }
 
 #[tokio::test]
@@ -110,3 +113,165 @@ async fn subscribe_returns_400_when_fields_is_present_but_invalid() {
         );
     }
 }
+
+#[tokio::test]
+async fn subscribing_twice_while_pending_resends_a_fresh_confirmation_email() {
+    let test_app = spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&test_app.email_server)
+        .await;
+
+    let first_response = test_app.subscribe_request(body.into()).await;
+    let second_response = test_app.subscribe_request(body.into()).await;
+
+    assert_eq!(200, first_response.status().as_u16());
+    assert_eq!(200, second_response.status().as_u16());
+
+    let email_requests = test_app.email_server.received_requests().await.unwrap();
+    let first_link = test_app.get_confirmation_link(&email_requests[0]);
+    let second_link = test_app.get_confirmation_link(&email_requests[1]);
+    assert_ne!(
+        first_link.html_confirmation_link,
+        second_link.html_confirmation_link
+    );
+
+    // Only the most recent link is valid.
+    let response = reqwest::get(first_link.html_confirmation_link)
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+    let response = reqwest::get(second_link.html_confirmation_link)
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn subscribing_with_a_confirmed_email_succeeds_without_sending_an_email() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.subscribe_request(body.into()).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&test_app.db_poll)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(SubscriptionStatus::Confirmed, saved.status);
+}
+
+#[tokio::test]
+async fn subscribing_again_after_unsubscribing_requires_a_new_confirmation() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    query!("UPDATE subscriptions SET status = 'unsubscribed'")
+        .execute(&test_app.db_poll)
+        .await
+        .unwrap();
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app.subscribe_request(body.into()).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let saved = query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&test_app.db_poll)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!(SubscriptionStatus::PendingConfirmation, saved.status);
+}
+
+#[tokio::test]
+async fn subscribing_with_json_returns_201_with_the_new_subscription() {
+    let test_app = spawn_app().await;
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_subscription_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com"
+        }))
+        .await;
+
+    assert_eq!(201, response.status().as_u16());
+    let location = response.headers()["Location"].to_str().unwrap().to_owned();
+    let subscription: Subscription = response.json().await.unwrap();
+    assert_eq!(
+        format!("/api/v1/subscriptions/{}", subscription.id),
+        location
+    );
+    assert_eq!(SubscriptionStatus::PendingConfirmation, subscription.status);
+
+    let response = test_app.get_subscription(&location).await;
+    assert_eq!(200, response.status().as_u16());
+    let fetched: Subscription = response.json().await.unwrap();
+    assert_eq!(subscription.id, fetched.id);
+    assert_eq!(subscription.subscribed_at, fetched.subscribed_at);
+}
+
+#[tokio::test]
+async fn subscribing_an_address_already_on_the_list_with_json_returns_202() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+
+    let response = test_app
+        .post_subscription_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "ursula_le_guin@gmail.com"
+        }))
+        .await;
+
+    assert_eq!(202, response.status().as_u16());
+    assert!(response.headers().get("Location").is_none());
+    assert_eq!("", response.text().await.unwrap());
+}
+
+#[tokio::test]
+async fn invalid_json_subscriptions_are_rejected_with_field_errors() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .post_subscription_json(&serde_json::json!({
+            "name": "le guin",
+            "email": "not-an-email"
+        }))
+        .await;
+
+    assert_eq!(400, response.status().as_u16());
+    let problem: serde_json::Value = response.json().await.unwrap();
+    assert_eq!("email.invalid", problem["errors"][0]["code"]);
+}
+
+#[tokio::test]
+async fn an_unknown_subscription_is_a_404() {
+    let test_app = spawn_app().await;
+
+    let response = test_app
+        .get_subscription(&format!("/api/v1/subscriptions/{}", uuid::Uuid::new_v4()))
+        .await;
+
+    assert_eq!(404, response.status().as_u16());
+}
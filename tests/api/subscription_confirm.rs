@@ -1,6 +1,11 @@
-use crate::helpers::spawn_app;
+use crate::helpers::{
+    create_unconfirmed_subscriber, spawn_app, spawn_app_with, ConfirmationLink, TestApp,
+};
+use chrono::{Duration, Utc};
 use wiremock::matchers::{method, path};
 use wiremock::{Mock, Request, ResponseTemplate};
+use zero2prod::action_links::LinkAction;
+use zero2prod::domain::SubscriptionStatus;
 
 #[tokio::test]
 async fn confirmation_without_token_reject_with_400() {
@@ -60,12 +65,207 @@ async fn clicking_on_the_confirmation_link_confirms_a_subscriber() {
         .error_for_status()
         .unwrap();
 
-    let saved = sqlx::query!("SELECT email, name, status FROM subscriptions")
-        .fetch_one(&test_app.db_poll)
-        .await
-        .expect("Failed to fetch saved subscription");
+    let saved = sqlx::query!(
+        r#"SELECT email, name, status AS "status: SubscriptionStatus" FROM subscriptions"#
+    )
+    .fetch_one(&test_app.db_poll)
+    .await
+    .expect("Failed to fetch saved subscription");
 
     assert_eq!("le guin", saved.name);
     assert_eq!("ursula_le_guin@gmail.com", saved.email);
-    assert_eq!("confirmed", saved.status);
+    assert_eq!(SubscriptionStatus::Confirmed, saved.status);
+}
+
+fn subscription_token(confirmation_link: &ConfirmationLink) -> String {
+    confirmation_link
+        .html_confirmation_link
+        .query_pairs()
+        .find(|(name, _)| name == "subscription_token")
+        .map(|(_, value)| value.into_owned())
+        .unwrap()
+}
+
+async fn expire_subscription_tokens(test_app: &TestApp) {
+    sqlx::query!("UPDATE subscription_tokens SET expires_at = now() - interval '1 hour'")
+        .execute(&test_app.db_poll)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn subscription_tokens_expire_after_the_configured_ttl() {
+    let test_app =
+        spawn_app_with(|c| c.application_settings.subscription_token_ttl_seconds = 3600).await;
+
+    create_unconfirmed_subscriber(&test_app).await;
+
+    let saved = sqlx::query!(
+        r#"SELECT extract(epoch FROM expires_at - created_at)::float8 AS "ttl!" FROM subscription_tokens"#
+    )
+    .fetch_one(&test_app.db_poll)
+    .await
+    .unwrap();
+    assert!((saved.ttl - 3600.0).abs() < 5.0);
+}
+
+#[tokio::test]
+async fn a_confirmation_link_can_only_be_used_once() {
+    let test_app = spawn_app().await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+
+    let response = reqwest::get(confirmation_link.html_confirmation_link.clone())
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    let response = reqwest::get(confirmation_link.html_confirmation_link)
+        .await
+        .unwrap();
+    assert_eq!(409, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn an_expired_confirmation_link_is_rejected_with_410_and_a_resend_form() {
+    let test_app = spawn_app().await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+    expire_subscription_tokens(&test_app).await;
+
+    let response = reqwest::get(confirmation_link.html_confirmation_link.clone())
+        .await
+        .unwrap();
+    assert_eq!(410, response.status().as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(r#"action="/subscriptions/confirm/resend""#));
+    assert!(html_page.contains(&subscription_token(&confirmation_link)));
+
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    assert_eq!(SubscriptionStatus::PendingConfirmation, saved.status);
+}
+
+#[tokio::test]
+async fn resending_a_confirmation_sends_a_fresh_working_link() {
+    let test_app = spawn_app().await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+    expire_subscription_tokens(&test_app).await;
+
+    Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_resend_confirmation(&subscription_token(&confirmation_link))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let new_link = test_app.get_confirmation_link(&email_request);
+    assert_ne!(
+        confirmation_link.html_confirmation_link,
+        new_link.html_confirmation_link
+    );
+
+    let response = reqwest::get(confirmation_link.html_confirmation_link)
+        .await
+        .unwrap();
+    assert_eq!(401, response.status().as_u16());
+
+    let response = reqwest::get(new_link.html_confirmation_link).await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn resending_a_confirmation_for_a_confirmed_subscriber_sends_nothing() {
+    let test_app = spawn_app().await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+    reqwest::get(confirmation_link.html_confirmation_link.clone())
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+
+    Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    let response = test_app
+        .post_resend_confirmation(&subscription_token(&confirmation_link))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn resending_a_confirmation_with_an_unknown_token_is_rejected() {
+    let test_app = spawn_app().await;
+
+    let response = test_app.post_resend_confirmation("unknown").await;
+
+    assert_eq!(401, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn only_a_digest_of_the_subscription_token_is_stored() {
+    let test_app = spawn_app_with(|c| c.application_settings.subscription_token_length = 40).await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+    let subscription_token = subscription_token(&confirmation_link);
+
+    let saved = sqlx::query!("SELECT subscription_token_hash FROM subscription_tokens")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+
+    assert_eq!(40, subscription_token.len());
+    assert_ne!(subscription_token, saved.subscription_token_hash);
+    assert_eq!(64, saved.subscription_token_hash.len());
+
+    let response = reqwest::get(confirmation_link.html_confirmation_link)
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn a_confirmation_link_cannot_resubscribe_someone_who_left() {
+    let test_app = spawn_app().await;
+    let confirmation_link = create_unconfirmed_subscriber(&test_app).await;
+    let subscriber = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    let unsubscribe_token = test_app.action_link_signer.sign(
+        subscriber.id,
+        LinkAction::Unsubscribe,
+        Utc::now() + Duration::hours(1),
+    );
+    test_app
+        .post_unsubscribe(&unsubscribe_token)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let response = reqwest::get(confirmation_link.html_confirmation_link)
+        .await
+        .unwrap();
+
+    assert_eq!(410, response.status().as_u16());
+    let saved = sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap();
+    assert_eq!(SubscriptionStatus::Unsubscribed, saved.status);
 }
@@ -0,0 +1,206 @@
+use crate::helpers::{create_confirmed_subscriber, spawn_app, TestApp};
+use chrono::{Duration, Utc};
+use uuid::Uuid;
+use wiremock::matchers::{any, method, path};
+use wiremock::{Mock, ResponseTemplate};
+use zero2prod::action_links::LinkAction;
+use zero2prod::domain::SubscriptionStatus;
+
+async fn get_subscriber_id(test_app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&test_app.db_poll)
+        .await
+        .expect("Failed to fetch the subscriber.")
+        .id
+}
+
+async fn sign_unsubscribe_token(test_app: &TestApp) -> String {
+    test_app.action_link_signer.sign(
+        get_subscriber_id(test_app).await,
+        LinkAction::Unsubscribe,
+        Utc::now() + Duration::days(1),
+    )
+}
+
+async fn get_status(test_app: &TestApp) -> SubscriptionStatus {
+    sqlx::query!(r#"SELECT status AS "status: SubscriptionStatus" FROM subscriptions"#)
+        .fetch_one(&test_app.db_poll)
+        .await
+        .unwrap()
+        .status
+}
+
+async fn publish_newsletter(test_app: &TestApp) {
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    });
+    let response = test_app.post_newsletters(newsletter_request_body).await;
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn newsletter_issues_carry_an_unsubscribe_link_and_one_click_headers() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+
+    Mock::given(path("/v3/smtp/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_newsletter(&test_app).await;
+    test_app.dispatch_all_pending_emails().await;
+
+    let email_request = test_app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&email_request.body).unwrap();
+    let list_unsubscribe = body["headers"]["List-Unsubscribe"].as_str().unwrap();
+    let unsubscribe_link = list_unsubscribe
+        .strip_prefix('<')
+        .and_then(|link| link.strip_suffix('>'))
+        .unwrap();
+    let token = unsubscribe_link
+        .strip_prefix(&format!(
+            "{}/subscriptions/unsubscribe?token=",
+            test_app.base_url
+        ))
+        .unwrap();
+    assert!(body["htmlContent"]
+        .as_str()
+        .unwrap()
+        .contains(unsubscribe_link));
+    assert!(body["textContent"]
+        .as_str()
+        .unwrap()
+        .contains(unsubscribe_link));
+    assert_eq!(
+        "List-Unsubscribe=One-Click",
+        body["headers"]["List-Unsubscribe-Post"]
+    );
+
+    let response = test_app.post_unsubscribe(token).await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        SubscriptionStatus::Unsubscribed,
+        get_status(&test_app).await
+    );
+}
+
+#[tokio::test]
+async fn unsubscribe_without_token_is_rejected_with_400() {
+    let test_app = spawn_app().await;
+
+    let response = reqwest::get(format!("{}/subscriptions/unsubscribe", test_app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unsubscribe_with_an_unknown_token_is_rejected_with_401() {
+    let test_app = spawn_app().await;
+
+    let get_response = test_app.get_unsubscribe("not-a-real-token").await;
+    let post_response = test_app.post_unsubscribe("not-a-real-token").await;
+
+    assert_eq!(401, get_response.status().as_u16());
+    assert_eq!(401, post_response.status().as_u16());
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_asks_for_confirmation_without_unsubscribing() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let token = sign_unsubscribe_token(&test_app).await;
+
+    let response = test_app.get_unsubscribe(&token).await;
+
+    assert_eq!(200, response.status().as_u16());
+    let html_page = response.text().await.unwrap();
+    assert!(html_page.contains(&format!(
+        r#"<form action="/subscriptions/unsubscribe?token={}" method="post">"#,
+        token
+    )));
+    assert_eq!(SubscriptionStatus::Confirmed, get_status(&test_app).await);
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_marks_the_subscriber_as_unsubscribed() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let token = sign_unsubscribe_token(&test_app).await;
+
+    let response = test_app.post_unsubscribe(&token).await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        SubscriptionStatus::Unsubscribed,
+        get_status(&test_app).await
+    );
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_do_not_receive_newsletters() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let token = sign_unsubscribe_token(&test_app).await;
+    test_app.post_unsubscribe(&token).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_newsletter(&test_app).await;
+    test_app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn queued_issues_are_not_delivered_after_unsubscribing() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let token = sign_unsubscribe_token(&test_app).await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&test_app.email_server)
+        .await;
+
+    publish_newsletter(&test_app).await;
+    test_app.post_unsubscribe(&token).await;
+    test_app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn expired_or_forged_signed_tokens_are_rejected_with_401() {
+    let test_app = spawn_app().await;
+    create_confirmed_subscriber(&test_app).await;
+    let expired = test_app.action_link_signer.sign(
+        get_subscriber_id(&test_app).await,
+        LinkAction::Unsubscribe,
+        Utc::now() - Duration::seconds(1),
+    );
+    let token = sign_unsubscribe_token(&test_app).await;
+    let (payload, _) = token.split_once('.').unwrap();
+    let forged = format!("{}.{}", payload, "A".repeat(43));
+
+    for token in [expired, forged] {
+        let response = test_app.post_unsubscribe(&token).await;
+        assert_eq!(401, response.status().as_u16());
+    }
+    assert_eq!(SubscriptionStatus::Confirmed, get_status(&test_app).await);
+}